@@ -0,0 +1,124 @@
+//! Measures the construction of the LR automaton and its data tables for the bundled
+//! grammars, and the memory used by the tables before and after compression. The sizes of
+//! the automata are compared with the baseline recorded in `benches/baseline.toml`, because
+//! they don't depend on the machine.
+//! Run with `cargo bench --bench automaton`.
+
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use syn::automaton::Automaton;
+use syn::grammar;
+use toml::Table;
+
+/// Number of measured runs for each grammar.
+const RUNS: usize = 5;
+
+/// The states, items and derived closure items of the automata before the kernel index.
+const BASELINE: &str = include_str!("baseline.toml");
+
+fn main() {
+    let mut paths: Vec<PathBuf> = fs::read_dir("grammar")
+        .expect("Unable to read the grammar directory")
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "toml")
+        })
+        .collect();
+
+    paths.sort();
+
+    let baseline: Table = BASELINE.parse().expect("Unable to parse the baseline");
+    let mut comparisons = Vec::new();
+
+    println!(
+        "{:<20} {:>8} {:>10} {:>12} {:>12} {:>12} {:>12} {:>8}",
+        "grammar", "states", "items", "automaton", "tables", "hashed", "compressed", "saved"
+    );
+
+    for path in paths {
+        let grammar = match grammar::read_file(&path) {
+            Ok(grammar) => grammar,
+            Err(_) => continue,
+        };
+
+        let mut automaton_time = Duration::MAX;
+        let mut tables_time = Duration::MAX;
+        let mut size = (0, 0);
+        let mut closures = 0;
+        let mut memory = (0, 0);
+
+        for _ in 0..RUNS {
+            let start = Instant::now();
+            let automaton = Automaton::new(&grammar, 0);
+            automaton_time = automaton_time.min(start.elapsed());
+
+            let start = Instant::now();
+            let data = automaton.data();
+            tables_time = tables_time.min(start.elapsed());
+
+            size = automaton.size();
+            closures = automaton.closure_size();
+            memory = data.map_or((0, 0), |data| data.memory());
+        }
+
+        let saved = if memory.0 > 0 {
+            format!("{:.0}%", 100.0 - 100.0 * memory.1 as f64 / memory.0 as f64)
+        } else {
+            String::from("-")
+        };
+
+        let name = path.file_name().unwrap().to_string_lossy();
+
+        println!(
+            "{:<20} {:>8} {:>10} {:>12.2?} {:>12.2?} {:>9} KiB {:>8} KiB {:>8}",
+            name,
+            size.0,
+            size.1,
+            automaton_time,
+            tables_time,
+            memory.0.div_ceil(1024),
+            memory.1.div_ceil(1024),
+            saved
+        );
+
+        if let Some(base) = baseline.get(name.as_ref()).and_then(|base| base.as_table()) {
+            let field = |key| {
+                base.get(key)
+                    .and_then(|value| value.as_integer())
+                    .unwrap_or(0)
+            };
+
+            comparisons.push(format!(
+                "{:<20} {:>8} {:>12} {:>10} {:>12} {:>12} {:>14} {:>7.1}x",
+                name,
+                size.0,
+                field("states"),
+                size.1,
+                field("items"),
+                closures,
+                field("closures"),
+                field("closures") as f64 / closures as f64
+            ));
+        }
+    }
+
+    println!();
+    println!(
+        "{:<20} {:>8} {:>12} {:>10} {:>12} {:>12} {:>14} {:>8}",
+        "grammar",
+        "states",
+        "base states",
+        "items",
+        "base items",
+        "closures",
+        "base closures",
+        "fewer"
+    );
+
+    for comparison in comparisons {
+        println!("{}", comparison);
+    }
+}
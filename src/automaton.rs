@@ -1,28 +1,30 @@
+use std::collections::HashMap;
 use std::collections::hash_map::Entry;
-use std::collections::{HashMap, HashSet, VecDeque};
 use std::error;
 use std::fmt::{self, Display, Formatter};
 
-use indexmap::IndexSet;
-
 use crate::grammar::{Grammar, Symbol};
-use crate::util::{self, AsString};
+use crate::util::{self, AsString, BitSet};
 
 mod action;
+mod compact;
 mod data;
 mod item;
+mod shared;
 mod state;
 mod transition;
 
 pub use action::Action;
+pub use compact::Compact;
 pub use data::{Data, Table};
 pub use item::Item;
+pub use shared::Shared;
 use state::State;
 use transition::{ItemTransition, StateTransition};
 
 /// The `Automaton` struct describes the automaton for a grammar.
-pub struct Automaton {
-    grammar: Grammar,
+pub struct Automaton<'a> {
+    grammar: &'a Grammar,
     start_rule: usize,
     states: Vec<State>,
     state_transitions: Vec<StateTransition>,
@@ -30,79 +32,21 @@ pub struct Automaton {
     item_transitions: Vec<ItemTransition>,
 }
 
-impl Automaton {
+impl<'a> Automaton<'a> {
     /// Constructs a new automaton.
-    pub fn new(grammar: &Grammar, rule: usize) -> Automaton {
-        let mut queue = VecDeque::new();
-
-        let mut states = IndexSet::new();
-        let mut state_transitions = HashSet::new();
-        let mut items = IndexSet::new();
-        let mut item_transitions = HashSet::new();
-
-        // Construct the initial state.
-        let start_rule = grammar.rule(rule);
-
-        for &lookahead in &start_rule.follow {
-            items.insert(Item::initial(items.len(), start_rule, lookahead));
-        }
-
-        let initial_state = State::new(0, (0..items.len()).collect());
-        queue.push_back((initial_state.id, Symbol::End.id()));
-        states.insert(initial_state);
-
-        while let Some((id, symbol)) = queue.pop_front() {
-            let state = states.get_index(id).unwrap();
-
-            // Derive the next state from the current state
-            // using the specified transition symbol.
-            let (mut next_state, transitions) = state
-                .derive(symbol, grammar, &mut items, states.len())
-                .unwrap();
-
-            let mut state_transition = StateTransition::new(state.id, next_state.id, symbol);
-
-            // If the derived state already exists, save the
-            // derived transitions using the existing ID.
-            if let Some(existing) = states.get(&next_state) {
-                state_transition.to = existing.id;
-                state_transitions.insert(state_transition);
-
-                for mut transition in transitions {
-                    if transition.from.0 == next_state.id {
-                        transition.from.0 = existing.id;
-                    }
-
-                    if transition.to.0 == next_state.id {
-                        transition.to.0 = existing.id;
-                    }
-
-                    item_transitions.insert(transition);
-                }
-
-                continue;
-            }
-
-            next_state.id = states.len();
-            states.insert(next_state.clone());
-
-            state_transitions.insert(state_transition);
-            item_transitions.extend(transitions);
+    pub fn new(grammar: &'a Grammar, rule: usize) -> Automaton<'a> {
+        Shared::new().automaton(grammar, rule)
+    }
 
-            // Add the state with its transition symbols to the queue.
-            for symbol in next_state.transitions(&items) {
-                queue.push_back((next_state.id, symbol));
-            }
-        }
+    /// Returns the number of states and items in the automaton.
+    pub fn size(&self) -> (usize, usize) {
+        (self.states.len(), self.items.len())
+    }
 
-        Automaton {
-            grammar: grammar.clone(),
-            start_rule: start_rule.id,
-            states: util::to_sorted_vec(states),
-            state_transitions: util::to_sorted_vec(state_transitions),
-            items: util::to_sorted_vec(items),
-            item_transitions: util::to_sorted_vec(item_transitions),
-        }
+    /// Returns the number of items in the closures of all states. Each state is derived once
+    /// from its kernel, so an item is counted for each state which contains it.
+    pub fn closure_size(&self) -> usize {
+        self.states.iter().map(|state| state.items.len()).sum()
     }
 
     /// Returns all automaton data tables.
@@ -110,7 +54,6 @@ impl Automaton {
         let action_table = self.action_table()?;
 
         Ok(Data::new(
-            &self.grammar,
             self.start_rule,
             &self.items,
             action_table,
@@ -133,10 +76,10 @@ impl Automaton {
                     .iter()
                     .enumerate()
                     .map(|(idx, &id)| {
-                        let mut item = self.items[id];
+                        let mut item = self.items[id].clone();
                         item.id = idx;
 
-                        let label = item.string(&self.grammar).replace("\"", "\\\"");
+                        let label = item.string(self.grammar).replace("\"", "\\\"");
                         format!("<{}> {}", item.id, label)
                     })
                     .collect::<Vec<String>>()
@@ -214,36 +157,37 @@ impl Automaton {
 
         for state in &self.states {
             for &id in &state.items {
-                let item = self.items[id];
+                let item = &self.items[id];
 
                 // Accept actions have a higher precedence.
-                let (symbol, action) = if item.can_accept(self.start_rule) {
-                    let symbol = item.head.unwrap_or(item.lookahead);
-                    (symbol, Action::Accept(item.rule))
+                let action = if item.can_accept(self.start_rule) {
+                    Action::Accept(item.rule)
                 } else if item.can_reduce(self.start_rule) {
-                    (item.lookahead, Action::Reduce(item.rule))
+                    Action::Reduce(item.rule)
                 } else {
                     continue;
                 };
 
-                let key = (state.id, symbol);
+                for symbol in item.lookaheads.iter() {
+                    let key = (state.id, symbol);
 
-                // Every symbol in each state can only correspond to a single action.
-                if let Entry::Occupied(mut entry) = action_table.entry(key) {
-                    // Resolve conflicts if the grammar has specified an override.
-                    if let Some(preference) = self.grammar.actions.get(&symbol) {
-                        if preference.is_reduce() {
-                            entry.insert(action);
+                    // Every symbol in each state can only correspond to a single action.
+                    if let Entry::Occupied(mut entry) = action_table.entry(key) {
+                        // Resolve conflicts if the grammar has specified an override.
+                        if let Some(preference) = self.grammar.actions.get(&symbol) {
+                            if preference.is_reduce() {
+                                entry.insert(action);
+                            }
+
+                            continue;
                         }
 
-                        continue;
+                        let symbol = self.grammar.symbol(symbol).clone();
+                        return Err(Error::ActionConflict(state.id, symbol));
                     }
 
-                    let symbol = self.grammar.symbol(symbol).clone();
-                    return Err(Error::ActionConflict(state.id, symbol));
+                    action_table.insert(key, action);
                 }
-
-                action_table.insert(key, action);
             }
         }
 
@@ -267,19 +211,23 @@ impl Automaton {
     /// The LEFT table describes which item a symbol in a state corresponds to.
     /// A symbol corresponds to an item, if it's in its FIRST set.
     fn left_table(&self) -> Table<usize> {
+        // Find the FIRST set of each item's follow sequence, followed by its lookaheads.
+        let firsts: Vec<BitSet> = self
+            .items
+            .iter()
+            .map(|item| {
+                let sequence = item.follow(self.grammar.rule(item.rule));
+                self.grammar.first_lookahead_set(sequence, &item.lookaheads)
+            })
+            .collect();
+
         self.states
             .iter()
             .flat_map(|state| {
                 state.items.iter().fold(HashMap::new(), |mut acc, &id| {
-                    let item = self.items[id];
-
-                    // Find the FIRST set of the item's follow sequence.
-                    let sequence = item.follow(self.grammar.rule(item.rule));
-                    let first = self.grammar.first_sequence(&sequence);
-
-                    for symbol in first {
-                        let items: &mut Vec<Item> = acc.entry((state.id, symbol)).or_default();
-                        items.push(item);
+                    for symbol in firsts[id].iter() {
+                        let items: &mut Vec<usize> = acc.entry((state.id, symbol)).or_default();
+                        items.push(id);
                     }
 
                     acc
@@ -288,9 +236,9 @@ impl Automaton {
             .filter(|(_, items)| {
                 // The LEFT table only contains symbols
                 // that correspond to a single, unique item.
-                items.len() == 1 && items[0].unique
+                items.len() == 1 && self.items[items[0]].unique
             })
-            .map(|(key, items)| (key, items[0].id))
+            .map(|(key, items)| (key, items[0]))
             .collect()
     }
 
@@ -306,7 +254,7 @@ impl Automaton {
     }
 }
 
-impl Display for Automaton {
+impl Display for Automaton<'_> {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         let states = format!(
             "States\n{}\n\nState transitions\n{}",
@@ -315,13 +263,13 @@ impl Display for Automaton {
                 .map(|item| item.string(self))
                 .collect::<Vec<String>>()
                 .join("\n"),
-            util::as_string(&self.state_transitions, &self.grammar, "\n")
+            util::as_string(&self.state_transitions, self.grammar, "\n")
         );
 
         let items = format!(
             "Items\n{}\n\nItem transitions\n{}",
-            util::as_string(&self.items, &self.grammar, "\n"),
-            util::as_string(&self.item_transitions, &self.grammar, "\n")
+            util::as_string(&self.items, self.grammar, "\n"),
+            util::as_string(&self.item_transitions, self.grammar, "\n")
         );
 
         write!(f, "{}\n\n{}", states, items)
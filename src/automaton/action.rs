@@ -1,7 +1,10 @@
 use std::fmt::{self, Display, Formatter};
 
+use crate::generator::Literal;
+use crate::util::encoding::{Decoder, Encode, Encoder, Error};
+
 /// The `Action` enum in the ACTION table.
-#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub enum Action {
     /// Shift the automaton to the next state.
     Shift(usize),
@@ -34,3 +37,34 @@ impl Display for Action {
         }
     }
 }
+
+impl Encode for Action {
+    fn encode(&self, encoder: &mut Encoder) {
+        let (kind, value): (usize, usize) = match *self {
+            Self::Shift(state) => (0, state),
+            Self::Reduce(rule) => (1, rule),
+            Self::Accept(rule) => (2, rule),
+        };
+
+        encoder.write(&(kind, value));
+    }
+
+    fn decode(decoder: &mut Decoder) -> Result<Action, Error> {
+        match decoder.read::<(usize, usize)>()? {
+            (0, state) => Ok(Self::Shift(state)),
+            (1, rule) => Ok(Self::Reduce(rule)),
+            (2, rule) => Ok(Self::Accept(rule)),
+            _ => Err(Error::Invalid),
+        }
+    }
+}
+
+impl Literal for Action {
+    fn literal(&self) -> String {
+        match self {
+            Self::Shift(state) => format!("Action::Shift({})", state),
+            Self::Reduce(rule) => format!("Action::Reduce({})", rule),
+            Self::Accept(rule) => format!("Action::Accept({})", rule),
+        }
+    }
+}
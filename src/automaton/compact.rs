@@ -0,0 +1,306 @@
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::mem;
+
+use crate::generator::Literal;
+use crate::util::encoding::{Decoder, Encode, Encoder, Error};
+
+use super::data::Table;
+
+/// Marks an empty row, or a slot that isn't used by any row.
+const EMPTY: usize = usize::MAX;
+
+/// The `Compact` struct is a parse table compressed with row displacement.
+///
+/// The columns with at least one entry are numbered consecutively. The rows of the
+/// table are overlapped in a single array, and each slot records the offset of the row
+/// it belongs to, so a lookup only needs a few array accesses. Identical rows share
+/// the same offset. A row can also have a default value, which is returned for all
+/// columns without an entry.
+#[derive(Clone, Debug)]
+pub struct Compact<T> {
+    columns: Vec<usize>,
+    offsets: Vec<usize>,
+    checks: Vec<usize>,
+    values: Vec<T>,
+    defaults: Vec<Option<T>>,
+    len: usize,
+}
+
+impl<T: Copy + Eq + Hash + Ord> Compact<T> {
+    /// Constructs a new compressed table without default values.
+    pub fn new(table: &Table<T>) -> Compact<T> {
+        Compact::with_defaults(table, |_| false)
+    }
+
+    /// Constructs a new compressed table. The most common value in each row,
+    /// which satisfies the predicate, becomes the default value of the row.
+    pub fn with_defaults(table: &Table<T>, is_default: impl Fn(&T) -> bool) -> Compact<T> {
+        let count = table.keys().map(|&(row, _)| row + 1).max().unwrap_or(0);
+        let mut rows: Vec<Vec<(usize, T)>> = vec![Vec::new(); count];
+
+        // Number the columns with entries in ascending order.
+        let mut columns = vec![
+            EMPTY;
+            table
+                .keys()
+                .map(|&(_, column)| column + 1)
+                .max()
+                .unwrap_or(0)
+        ];
+
+        for &(_, column) in table.keys() {
+            columns[column] = 0;
+        }
+
+        (columns.iter_mut().filter(|column| **column != EMPTY))
+            .enumerate()
+            .for_each(|(idx, column)| *column = idx);
+
+        for (&(row, column), &value) in table {
+            rows[row].push((columns[column], value));
+        }
+
+        let mut defaults = Vec::new();
+
+        if table.values().any(&is_default) {
+            defaults = rows
+                .iter_mut()
+                .map(|entries| {
+                    let default = most_common(entries.iter().map(|(_, value)| *value), &is_default);
+
+                    if default.is_some() {
+                        entries.retain(|(_, value)| Some(*value) != default);
+                    }
+
+                    default
+                })
+                .collect();
+        }
+
+        // Place the rows with the most entries first, as they are the hardest to fit.
+        let mut order: Vec<usize> = (0..count).collect();
+        order.sort_by_key(|&row| Reverse(rows[row].len()));
+
+        let mut offsets = vec![EMPTY; count];
+        let mut checks: Vec<usize> = Vec::new();
+        let mut values: Vec<T> = Vec::new();
+
+        // The offsets of the rows that are already placed.
+        let mut placed: HashMap<&[(usize, T)], usize> = HashMap::new();
+        let mut used: Vec<bool> = Vec::new();
+        // The index of the first empty slot.
+        let mut free: usize = 0;
+
+        for row in &mut rows {
+            row.sort_unstable_by_key(|&(column, _)| column);
+        }
+
+        for row in order {
+            let entries = rows[row].as_slice();
+
+            if entries.is_empty() {
+                continue;
+            }
+
+            if let Some(&offset) = placed.get(entries) {
+                offsets[row] = offset;
+                continue;
+            }
+
+            // Find the first unused offset where all entries fit into empty slots.
+            let mut offset = free.saturating_sub(entries[0].0);
+
+            while !entries.iter().all(|&(column, _)| {
+                checks
+                    .get(offset + column)
+                    .is_none_or(|&check| check == EMPTY)
+            }) || used.get(offset).is_some_and(|&used| used)
+            {
+                offset += 1;
+            }
+
+            let length = offset + entries.last().unwrap().0 + 1;
+
+            if length > checks.len() {
+                checks.resize(length, EMPTY);
+                values.resize(length, entries[0].1);
+            }
+
+            for &(column, value) in entries {
+                checks[offset + column] = offset;
+                values[offset + column] = value;
+            }
+
+            offsets[row] = offset;
+            placed.insert(entries, offset);
+            if offset >= used.len() {
+                used.resize(offset + 1, false);
+            }
+
+            used[offset] = true;
+
+            while checks.get(free).is_some_and(|&check| check != EMPTY) {
+                free += 1;
+            }
+        }
+
+        Compact {
+            columns,
+            offsets,
+            checks,
+            values,
+            defaults,
+            len: table.len(),
+        }
+    }
+
+    /// Returns the value in the row and column, or the default value of the row.
+    pub fn get(&self, row: usize, column: usize) -> Option<T> {
+        let offset = self.offsets.get(row).copied().unwrap_or(EMPTY);
+        let column = self.columns.get(column).copied().unwrap_or(EMPTY);
+        let idx = offset.checked_add(column);
+
+        match idx.and_then(|idx| self.checks.get(idx).map(|&check| (idx, check))) {
+            Some((idx, check)) if check == offset => Some(self.values[idx]),
+            _ => self.default(row),
+        }
+    }
+
+    /// Returns the default value of the row.
+    pub fn default(&self, row: usize) -> Option<T> {
+        self.defaults.get(row).copied().flatten()
+    }
+
+    /// Returns an iterator over the entries, which aren't covered by the default values.
+    pub fn iter(&self) -> impl Iterator<Item = ((usize, usize), T)> + '_ {
+        let columns: Vec<usize> = (0..self.columns.len())
+            .filter(|&column| self.columns[column] != EMPTY)
+            .collect();
+
+        let mut rows: HashMap<usize, Vec<usize>> = HashMap::new();
+
+        for (row, &offset) in self.offsets.iter().enumerate() {
+            rows.entry(offset).or_default().push(row);
+        }
+
+        self.checks
+            .iter()
+            .enumerate()
+            .filter(|&(_, &check)| check != EMPTY)
+            .flat_map(move |(idx, &offset)| {
+                let value = self.values[idx];
+                let column = columns[idx - offset];
+                let rows = rows[&offset].clone();
+                rows.into_iter().map(move |row| ((row, column), value))
+            })
+    }
+
+    /// Returns the number of rows in the table.
+    pub fn rows(&self) -> usize {
+        self.offsets.len()
+    }
+
+    /// Returns the number of entries in the uncompressed table.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Returns `true` if the uncompressed table is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns the number of bytes used by the compressed table.
+    pub fn memory(&self) -> usize {
+        self.columns.capacity() * mem::size_of::<usize>()
+            + self.offsets.capacity() * mem::size_of::<usize>()
+            + self.checks.capacity() * mem::size_of::<usize>()
+            + self.values.capacity() * mem::size_of::<T>()
+            + self.defaults.capacity() * mem::size_of::<Option<T>>()
+    }
+}
+
+impl<T: Encode> Encode for Compact<T> {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.write(&self.columns);
+        encoder.write(&self.offsets);
+        encoder.write(&self.checks);
+        encoder.write(&self.values);
+        encoder.write(&self.defaults);
+        encoder.write(&self.len);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Result<Compact<T>, Error> {
+        let table = Compact {
+            columns: decoder.read()?,
+            offsets: decoder.read()?,
+            checks: decoder.read()?,
+            values: decoder.read()?,
+            defaults: decoder.read()?,
+            len: decoder.read()?,
+        };
+
+        // Each slot has a value, so the lookups stay in bounds.
+        if table.checks.len() != table.values.len() {
+            return Err(Error::Invalid);
+        }
+
+        Ok(table)
+    }
+}
+
+impl<T: Literal> Literal for Compact<T> {
+    fn literal(&self) -> String {
+        // The generated parser defines the same marker for empty slots.
+        let indices = |indices: &[usize]| {
+            let indices: Vec<String> = (indices.iter())
+                .map(|&idx| match idx {
+                    EMPTY => "EMPTY".to_owned(),
+                    _ => idx.to_string(),
+                })
+                .collect();
+            format!("&[{}]", indices.join(", "))
+        };
+
+        format!(
+            "Compact {{ columns: {}, offsets: {}, checks: {}, values: {}, defaults: {} }}",
+            indices(&self.columns),
+            indices(&self.offsets),
+            indices(&self.checks),
+            self.values.literal(),
+            self.defaults.literal()
+        )
+    }
+}
+
+/// Returns the approximate number of bytes used by a hashed table with the number of entries.
+pub fn table_memory<T>(len: usize) -> usize {
+    if len == 0 {
+        return 0;
+    }
+
+    // Hash tables keep at most 7/8 of their buckets full, and the number of
+    // buckets is a power of two. Each bucket has an additional control byte.
+    let buckets = (len * 8).div_ceil(7).next_power_of_two();
+    buckets * (mem::size_of::<((usize, usize), T)>() + 1)
+}
+
+/// Returns the most common value which satisfies the predicate.
+/// Equally common values are resolved by the order of the values.
+fn most_common<T: Copy + Eq + Hash + Ord>(
+    values: impl Iterator<Item = T>,
+    predicate: impl Fn(&T) -> bool,
+) -> Option<T> {
+    let mut counts: HashMap<T, usize> = HashMap::new();
+
+    for value in values.filter(|value| predicate(value)) {
+        *counts.entry(value).or_default() += 1;
+    }
+
+    counts
+        .into_iter()
+        .max_by_key(|&(value, count)| (count, Reverse(value)))
+        .map(|(value, _)| value)
+}
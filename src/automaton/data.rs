@@ -1,30 +1,33 @@
 use std::collections::HashMap;
 
+use crate::generator::Literal;
 use crate::grammar::Grammar;
+use crate::util::encoding::{Decoder, Encode, Encoder, Error};
 use crate::util::{self, AsString};
 
 use super::action::Action;
+use super::compact::{self, Compact};
 use super::item::Item;
 
 /// The `Table` type represents a simplified version of an automaton.
 pub type Table<T> = HashMap<(usize, usize), T>;
 
-/// The `Data` struct contains all automaton data tables.
+/// The `Data` struct contains all automaton data tables, compressed for the parsers.
+/// Reductions are the default actions of the ACTION table, so a state reduces its most
+/// common rule for any lookahead symbol without a different action.
 #[derive(Clone, Debug)]
 pub struct Data {
-    pub grammar: Grammar,
     pub start_rule: usize,
     pub items: HashMap<usize, Item>,
-    pub action_table: Table<Action>,
-    pub goto_table: Table<usize>,
-    pub left_table: Table<usize>,
-    pub backtrack_table: Table<(usize, usize)>,
+    pub action_table: Compact<Action>,
+    pub goto_table: Compact<usize>,
+    pub left_table: Compact<usize>,
+    pub backtrack_table: Compact<(usize, usize)>,
 }
 
 impl Data {
     /// Constructs a new data table.
     pub fn new(
-        grammar: &Grammar,
         start_rule: usize,
         items: &[Item],
         action_table: Table<Action>,
@@ -35,46 +38,107 @@ impl Data {
         let items = backtrack_table
             .iter()
             .flat_map(|(to, from)| vec![to.1, from.1])
-            .map(|id| (id, items[id]))
+            .map(|id| (id, items[id].clone()))
             .collect();
 
         Data {
-            grammar: grammar.clone(),
             start_rule,
             items,
-            action_table,
-            goto_table,
-            left_table,
-            backtrack_table,
+            action_table: Compact::with_defaults(&action_table, Action::is_reduce),
+            goto_table: Compact::new(&goto_table),
+            left_table: Compact::new(&left_table),
+            backtrack_table: Compact::new(&backtrack_table),
         }
     }
+
+    /// Returns the approximate number of bytes used by the data tables
+    /// before and after compression.
+    pub fn memory(&self) -> (usize, usize) {
+        let hashed = compact::table_memory::<Action>(self.action_table.len())
+            + compact::table_memory::<usize>(self.goto_table.len())
+            + compact::table_memory::<usize>(self.left_table.len())
+            + compact::table_memory::<(usize, usize)>(self.backtrack_table.len());
+
+        let compressed = self.action_table.memory()
+            + self.goto_table.memory()
+            + self.left_table.memory()
+            + self.backtrack_table.memory();
+
+        (hashed, compressed)
+    }
+}
+
+impl Encode for Data {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.write(&self.start_rule);
+        encoder.write(&self.items);
+        encoder.write(&self.action_table);
+        encoder.write(&self.goto_table);
+        encoder.write(&self.left_table);
+        encoder.write(&self.backtrack_table);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Result<Data, Error> {
+        Ok(Data {
+            start_rule: decoder.read()?,
+            items: decoder.read()?,
+            action_table: decoder.read()?,
+            goto_table: decoder.read()?,
+            left_table: decoder.read()?,
+            backtrack_table: decoder.read()?,
+        })
+    }
+}
+
+impl Literal for Data {
+    fn literal(&self) -> String {
+        // The parsers only need the rule and the dot of the items.
+        let items = util::to_sorted_vec(self.items.values().map(|item| (item.id, item.key())));
+
+        format!(
+            "Data {{ start_rule: {}, items: {}, action_table: {}, goto_table: {}, \
+             left_table: {}, backtrack_table: {} }}",
+            self.start_rule,
+            items.literal(),
+            self.action_table.literal(),
+            self.goto_table.literal(),
+            self.left_table.literal(),
+            self.backtrack_table.literal()
+        )
+    }
 }
 
 impl AsString for Data {
     fn string(&self, grammar: &Grammar) -> String {
-        let action_table = util::to_sorted_vec(&self.action_table)
+        let defaults = (0..self.action_table.rows()).filter_map(|state| {
+            let action = self.action_table.default(state)?;
+            Some(format!("{}, * → {}", state, action))
+        });
+
+        let action_table = util::to_sorted_vec(self.action_table.iter())
             .iter()
-            .map(|&(&(state, symbol), action)| {
+            .map(|&((state, symbol), action)| {
                 format!("{}, {} → {}", state, grammar.symbol(symbol), action)
             })
+            .chain(defaults)
             .collect::<Vec<String>>()
             .join("\n");
 
-        let goto_table = util::to_sorted_vec(&self.goto_table)
+        let goto_table = util::to_sorted_vec(self.goto_table.iter())
             .iter()
-            .map(|&(&(from, symbol), to)| format!("{}, {} → {}", from, grammar.symbol(symbol), to))
+            .map(|&((from, symbol), to)| format!("{}, {} → {}", from, grammar.symbol(symbol), to))
             .collect::<Vec<String>>()
             .join("\n");
 
-        let left_table = util::to_sorted_vec(&self.left_table)
+        let left_table = util::to_sorted_vec(self.left_table.iter())
             .iter()
-            .map(|&(&(state, symbol), item)| {
+            .map(|&((state, symbol), item)| {
                 format!("{}, {} → {}", state, grammar.symbol(symbol), item)
             })
             .collect::<Vec<String>>()
             .join("\n");
 
-        let backtrack_table = util::to_sorted_vec(&self.backtrack_table)
+        let backtrack_table = util::to_sorted_vec(self.backtrack_table.iter())
             .iter()
             .map(|(to, from)| format!("{}, {} → {}, {}", to.0, to.1, from.0, from.1))
             .collect::<Vec<String>>()
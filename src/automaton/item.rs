@@ -2,16 +2,20 @@ use std::cmp::Ordering;
 use std::hash::{Hash, Hasher};
 
 use crate::grammar::{Grammar, Rule, Symbol};
-use crate::util::AsString;
+use crate::util::encoding::{Decoder, Encode, Encoder, Error};
+use crate::util::{AsString, BitSet};
 
 /// The `Item` struct describes an item of a state in an automaton.
 ///
+/// An item carries the set of all its lookahead symbols, so a state contains
+/// a single item for each position in a rule.
+///
 /// An item is unique, if all its parents are unique, and represent the same rule.
 /// If an item's parent is the item itself, it's not unique.
 ///
 /// To ensure the relation between `Eq` and `Ord`, the fields
 /// in the struct _must_ be unique for a specific `id`.
-#[derive(Clone, Copy, Debug, Eq)]
+#[derive(Clone, Debug, Eq)]
 pub struct Item {
     pub id: usize,
     pub rule: usize,
@@ -19,26 +23,26 @@ pub struct Item {
     pub dot: usize,
     // The symbol the `dot` field refers to.
     pub head: Option<usize>,
-    // The symbol that follows the item.
-    pub lookahead: usize,
+    // The symbols that can follow the item.
+    pub lookaheads: BitSet,
     pub unique: bool,
 }
 
 impl Item {
     /// Constructs the initial item.
-    pub fn initial(id: usize, rule: &Rule, lookahead: usize) -> Item {
+    pub fn initial(id: usize, rule: &Rule, lookaheads: BitSet) -> Item {
         Item {
             id,
             rule: rule.id,
             dot: 0,
             head: Some(rule.body[0]),
-            lookahead,
+            lookaheads,
             unique: true,
         }
     }
 
     /// Constructs a new item.
-    pub fn new(id: usize, rule: &Rule, lookahead: usize, unique: bool) -> Item {
+    pub fn new(id: usize, rule: &Rule, lookaheads: BitSet, unique: bool) -> Item {
         let mut dot = 0;
 
         // Ignore the starting $ symbol in wrapper rules.
@@ -51,7 +55,7 @@ impl Item {
             rule: rule.id,
             dot,
             head: Some(rule.body[dot]),
-            lookahead,
+            lookaheads,
             unique,
         }
     }
@@ -65,23 +69,23 @@ impl Item {
         }
     }
 
-    /// Returns all symbols following `head`, including the lookahead.
-    pub fn tail(&self, rule: &Rule) -> Vec<usize> {
-        let mut tail = if self.head.is_some() {
-            rule.tail(self.dot + 1).to_vec()
-        } else {
-            Vec::new()
-        };
+    /// Returns all symbols following `head`, without the lookaheads.
+    pub fn tail<'a>(&self, rule: &'a Rule) -> &'a [usize] {
+        match self.head {
+            Some(_) => rule.tail(self.dot + 1),
+            None => &[],
+        }
+    }
 
-        tail.push(self.lookahead);
-        tail
+    /// Returns `head` and all the symbols following it, without the lookaheads.
+    pub fn follow<'a>(&self, rule: &'a Rule) -> &'a [usize] {
+        rule.tail(self.dot)
     }
 
-    /// Returns `head` and all the symbols following it, including the lookahead.
-    pub fn follow(&self, rule: &Rule) -> Vec<usize> {
-        let mut follow = rule.tail(self.dot).to_vec();
-        follow.push(self.lookahead);
-        follow
+    /// Returns the rule and dot of the item, which identify
+    /// the item in a state regardless of its lookaheads and uniqueness.
+    pub fn key(&self) -> (usize, usize) {
+        (self.rule, self.dot)
     }
 
     /// Returns `true` if the head of the item is a nonterminal symbol.
@@ -114,7 +118,7 @@ impl PartialEq for Item {
     fn eq(&self, other: &Self) -> bool {
         self.rule == other.rule
             && self.dot == other.dot
-            && self.lookahead == other.lookahead
+            && self.lookaheads == other.lookaheads
             && self.unique == other.unique
     }
 }
@@ -135,7 +139,7 @@ impl Hash for Item {
     fn hash<H: Hasher>(&self, state: &mut H) {
         self.rule.hash(state);
         self.dot.hash(state);
-        self.lookahead.hash(state);
+        self.lookaheads.hash(state);
         self.unique.hash(state);
     }
 }
@@ -160,6 +164,13 @@ impl AsString for Item {
             body.push(dot);
         }
 
+        let lookaheads = self
+            .lookaheads
+            .iter()
+            .map(|id| grammar.symbol(id).to_string())
+            .collect::<Vec<String>>()
+            .join("/");
+
         let unique = if self.unique { "○" } else { "×" };
 
         format!(
@@ -167,8 +178,30 @@ impl AsString for Item {
             self.id,
             grammar.symbol(rule.head),
             body.join(" "),
-            grammar.symbol(self.lookahead),
+            lookaheads,
             unique
         )
     }
 }
+
+impl Encode for Item {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.write(&self.id);
+        encoder.write(&self.rule);
+        encoder.write(&self.dot);
+        encoder.write(&self.head);
+        encoder.write(&self.lookaheads);
+        encoder.write(&self.unique);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Result<Item, Error> {
+        Ok(Item {
+            id: decoder.read()?,
+            rule: decoder.read()?,
+            dot: decoder.read()?,
+            head: decoder.read()?,
+            lookaheads: decoder.read()?,
+            unique: decoder.read()?,
+        })
+    }
+}
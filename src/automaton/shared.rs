@@ -0,0 +1,206 @@
+use std::collections::{HashMap, VecDeque};
+
+use indexmap::IndexSet;
+
+use crate::grammar::Grammar;
+use crate::util;
+
+use super::Automaton;
+use super::item::Item;
+use super::state::State;
+use super::transition::{ItemTransition, StateTransition};
+
+/// The `Shared` struct contains the states of the automata for several start rules
+/// of the same grammar. The states with the same kernel items are constructed once,
+/// and shared by all automata which reach them.
+///
+/// The start rules may be added to the grammar between the constructions, as long as
+/// they don't change the rules of the existing symbols, like the LLLR wrapper rules.
+#[derive(Clone, Debug, Default)]
+pub struct Shared {
+    // The items of the states are in the order they were derived in.
+    states: Vec<State>,
+    items: IndexSet<Item>,
+    // The states indexed by the sorted IDs of their kernel items.
+    // The remaining items of a state are derived from its kernel,
+    // so states with the same kernel are equal.
+    kernels: HashMap<Vec<usize>, usize>,
+    // The transitions are grouped by the state they transition from.
+    state_transitions: Vec<Vec<StateTransition>>,
+    item_transitions: Vec<Vec<ItemTransition>>,
+}
+
+impl Shared {
+    /// Constructs a new set of shared states.
+    pub fn new() -> Shared {
+        Shared::default()
+    }
+
+    /// Returns the automaton for the start rule, constructing its missing states.
+    ///
+    /// The automaton only contains the states reachable from its initial state,
+    /// which are numbered in the same order as in a separately constructed automaton.
+    pub fn automaton<'a>(&mut self, grammar: &'a Grammar, rule: usize) -> Automaton<'a> {
+        let initial_state = self.insert(grammar, rule);
+
+        // Number the states in the order they're discovered, following
+        // the transitions of each state in the order of their symbols.
+        let mut state_ids = vec![None; self.states.len()];
+        let mut order = vec![initial_state];
+        state_ids[initial_state] = Some(0);
+
+        let mut idx = 0;
+
+        while idx < order.len() {
+            for transition in &self.state_transitions[order[idx]] {
+                if state_ids[transition.to].is_none() {
+                    state_ids[transition.to] = Some(order.len());
+                    order.push(transition.to);
+                }
+            }
+
+            idx += 1;
+        }
+
+        // Number the items in the order they first appear in the states. The items
+        // of each state are derived in the same order in every automaton.
+        let mut item_ids = vec![None; self.items.len()];
+        let mut items = Vec::new();
+
+        for &id in &order {
+            for &item in &self.states[id].items {
+                if item_ids[item].is_none() {
+                    item_ids[item] = Some(items.len());
+
+                    let mut item = self.items[item].clone();
+                    item.id = items.len();
+                    items.push(item);
+                }
+            }
+        }
+
+        let state_id = |id: usize| state_ids[id].unwrap();
+        let item_id = |(state, item): (usize, usize)| (state_id(state), item_ids[item].unwrap());
+
+        let states = order
+            .iter()
+            .map(|&id| {
+                let items = self.states[id].items.iter();
+                let items = util::to_sorted_vec(items.map(|&item| item_ids[item].unwrap()));
+                State::new(state_id(id), items)
+            })
+            .collect();
+
+        let state_transitions = order
+            .iter()
+            .flat_map(|&id| &self.state_transitions[id])
+            .map(|&StateTransition { from, to, symbol }| {
+                StateTransition::new(state_id(from), state_id(to), symbol)
+            });
+
+        let item_transitions = order.iter().flat_map(|&id| &self.item_transitions[id]).map(
+            |&ItemTransition { from, to, symbol }| {
+                ItemTransition::new(item_id(from), item_id(to), symbol)
+            },
+        );
+
+        Automaton {
+            grammar,
+            start_rule: rule,
+            states,
+            state_transitions: util::to_sorted_vec(state_transitions),
+            items,
+            item_transitions: util::to_sorted_vec(item_transitions),
+        }
+    }
+
+    /// Constructs the states reachable from the initial state of the start rule,
+    /// which don't exist yet, and returns the ID of the initial state.
+    fn insert(&mut self, grammar: &Grammar, rule: usize) -> usize {
+        let start_rule = grammar.rule(rule);
+        let lookaheads = start_rule.follow.iter().copied().collect();
+        let item = Item::initial(self.items.len(), start_rule, lookaheads);
+
+        let initial_item = match self.items.get_index_of(&item) {
+            Some(id) => id,
+            None => self.items.insert_full(item).0,
+        };
+
+        if let Some(&existing) = self.kernels.get(&vec![initial_item]) {
+            return existing;
+        }
+
+        // Construct the initial state.
+        let initial_state = State::new(self.states.len(), vec![initial_item]);
+        let initial_id = initial_state.id;
+        let mut queue = VecDeque::new();
+
+        for (symbol, kernel) in initial_state.kernels(grammar, &self.items) {
+            queue.push_back((initial_state.id, symbol, kernel));
+        }
+
+        self.kernels
+            .insert(initial_state.items.clone(), initial_state.id);
+        self.states.push(initial_state);
+        self.state_transitions.push(Vec::new());
+        self.item_transitions.push(Vec::new());
+
+        while let Some((id, symbol, kernel)) = queue.pop_front() {
+            let state = &self.states[id];
+
+            // If the next state already exists, save the
+            // kernel transitions using the existing ID.
+            if let Some(&existing) =
+                kernel_ids(&kernel, &self.items).and_then(|ids| self.kernels.get(&ids))
+            {
+                self.state_transitions[id].push(StateTransition::new(id, existing, symbol));
+
+                for (source, item) in &kernel {
+                    self.item_transitions[id].push(ItemTransition::new(
+                        (id, state.items[*source]),
+                        (existing, self.items.get_index_of(item).unwrap()),
+                        symbol,
+                    ));
+                }
+
+                continue;
+            }
+
+            // Derive the next state from the current state
+            // using the specified transition symbol.
+            let (next_state, transitions) =
+                state.derive(&kernel, symbol, grammar, &mut self.items, self.states.len());
+
+            self.state_transitions[id].push(StateTransition::new(id, next_state.id, symbol));
+            self.state_transitions.push(Vec::new());
+            self.item_transitions.push(Vec::new());
+
+            for transition in transitions {
+                self.item_transitions[transition.from.0].push(transition);
+            }
+
+            // Add the state with its transition symbols to the queue.
+            for (symbol, next_kernel) in next_state.kernels(grammar, &self.items) {
+                queue.push_back((next_state.id, symbol, next_kernel));
+            }
+
+            let ids = kernel_ids(&kernel, &self.items).unwrap();
+            self.kernels.insert(ids, next_state.id);
+            self.states.push(next_state);
+        }
+
+        initial_id
+    }
+}
+
+/// Returns the sorted global IDs of the kernel items,
+/// or `None` if any of the items doesn't exist yet.
+fn kernel_ids(kernel: &[(usize, Item)], items: &IndexSet<Item>) -> Option<Vec<usize>> {
+    let mut ids = kernel
+        .iter()
+        .map(|(_, item)| items.get_index_of(item))
+        .collect::<Option<Vec<usize>>>()?;
+
+    ids.sort_unstable();
+    Some(ids)
+}
@@ -1,16 +1,20 @@
 use std::cmp::Ordering;
-use std::collections::{HashSet, VecDeque};
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::hash::{Hash, Hasher};
 
 use indexmap::IndexSet;
 
 use crate::grammar::{Grammar, Symbol};
-use crate::util::{self, AsString};
+use crate::util::AsString;
 
 use super::Automaton;
 use super::item::Item;
 use super::transition::ItemTransition;
 
+/// `Kernel` describes the items a state is derived from,
+/// paired with the index of the item they were passed from.
+pub type Kernel = Vec<(usize, Item)>;
+
 /// The `State` struct describes a state in the automaton.
 ///
 /// To ensure the relation between `Eq` and `Ord`, the fields
@@ -27,139 +31,153 @@ impl State {
         State { id, items }
     }
 
-    /// Returns the list of transitions from the state.
-    pub fn transitions(&self, items: &IndexSet<Item>) -> Vec<usize> {
-        let transitions: HashSet<usize> = self
-            .items
-            .iter()
-            .filter_map(|&id| items.get_index(id).unwrap().head)
-            .filter(|&head| head != Symbol::Null.id())
-            .collect();
+    /// Returns the kernels of the next states with their transition symbols
+    /// in ascending order. The kernel items are paired with the index
+    /// of the item they were passed from, and have a temporary index-based ID.
+    ///
+    /// The kernel items are sorted by their rule and dot, so the items of the next
+    /// state are derived in the same order, regardless of the order of the items
+    /// in this state.
+    pub fn kernels(&self, grammar: &Grammar, items: &IndexSet<Item>) -> Vec<(usize, Kernel)> {
+        let mut kernels: BTreeMap<usize, Kernel> = BTreeMap::new();
+
+        for (idx, &id) in self.items.iter().enumerate() {
+            let mut item = items.get_index(id).unwrap().clone();
+
+            let head = match item.head {
+                Some(head) if head != Symbol::Null.id() => head,
+                _ => continue,
+            };
 
-        util::to_sorted_vec(transitions)
+            item.pass(grammar.rule(item.rule));
+            kernels.entry(head).or_default().push((idx, item));
+        }
+
+        for kernel in kernels.values_mut() {
+            kernel.sort_unstable_by_key(|(_, item)| item.key());
+
+            for (id, (_, item)) in kernel.iter_mut().enumerate() {
+                item.id = id;
+            }
+        }
+
+        kernels.into_iter().collect()
     }
 
-    /// Derives the next state with specified transition symbol.
+    /// Derives the next state from its kernel with specified transition symbol.
+    /// The items of the next state are in the order they were derived in.
     pub fn derive(
         &self,
+        kernel: &[(usize, Item)],
         symbol: usize,
         grammar: &Grammar,
         items: &mut IndexSet<Item>,
         state_id: usize,
-    ) -> Option<(State, HashSet<ItemTransition>)> {
-        // Keep all items which can transition with the symbol
-        // and assign them a temporary index-based ID.
-        let mut next_items: Vec<Item> = self
-            .items
-            .iter()
-            .enumerate()
-            .map(|(idx, &id)| {
-                let mut item = *items.get_index(id).unwrap();
-                item.id = idx;
-                item
-            })
-            .filter(|item| match item.head {
-                Some(head) => head == symbol,
-                None => false,
-            })
-            .collect();
-
-        if next_items.is_empty() {
-            return None;
-        }
-
-        let mut transitions: HashSet<ItemTransition> = HashSet::new();
-
-        // The buffer contains unique and non-unique versions
-        // of items so we can find existing items easier.
-        let mut buffer = HashSet::new();
+    ) -> (State, Vec<ItemTransition>) {
+        let mut next_items: Vec<Item> = Vec::with_capacity(kernel.len());
+        let mut transitions: Vec<ItemTransition> = Vec::new();
+
+        // The first parent and the children of each item, indexed by the temporary ID.
+        // Only the transitions within the next state are included.
+        let mut parents: Vec<Option<usize>> = Vec::new();
+        let mut children: Vec<Vec<usize>> = Vec::new();
+        // Whether the items have already derived their children.
+        let mut expanded: Vec<bool> = Vec::new();
+
+        // The buffer maps the rule and dot of the items to their temporary ID,
+        // so we can find existing items regardless of lookaheads and uniqueness.
+        let mut buffer = HashMap::new();
         let mut queue = VecDeque::new();
 
-        // Generate initial transitions and derive the items.
-        // Assign them a new temporary index-based ID.
-        for (idx, item) in next_items.iter_mut().enumerate() {
-            transitions.insert(ItemTransition::new(
-                (self.id, item.id),
-                (state_id, idx),
+        // Generate initial transitions from the kernel items.
+        for (source, item) in kernel {
+            transitions.push(ItemTransition::new(
+                (self.id, *source),
+                (state_id, item.id),
                 symbol,
             ));
 
-            item.id = idx;
-            item.pass(grammar.rule(item.rule));
-
             if item.at_nonterminal(&grammar.symbols) {
                 queue.push_back(item.id);
             }
 
-            let mut item = *item;
-            buffer.insert(item);
-            item.unique = !item.unique;
-            buffer.insert(item);
+            buffer.insert(item.key(), item.id);
+            next_items.push(item.clone());
+            parents.push(None);
+            children.push(Vec::new());
+            expanded.push(false);
         }
 
-        while let Some(item) = queue.pop_front() {
-            let item = next_items[item];
+        while let Some(id) = queue.pop_front() {
+            let item = &next_items[id];
+            let (head, unique) = (item.head.unwrap(), item.unique);
+
+            // Find the FIRST set of the symbol sequence that follows
+            // the current item's head, followed by its lookaheads.
+            let rule = grammar.rule(item.rule);
+            let lookaheads = grammar.first_lookahead_set(item.tail(rule), &item.lookaheads);
 
-            let head = item.head.unwrap();
-            let tail = item.tail(grammar.rule(item.rule));
-            // Find the FIRST set of the symbol sequence
-            // that follows the current item's head.
-            let lookaheads = grammar.first_sequence(&tail);
+            // An expanded item is only processed again when its lookaheads change,
+            // which must be propagated to its children.
+            let is_expanded = expanded[id];
+            expanded[id] = true;
 
             // Find all the grammar rules for the current item head.
             for rule in grammar.rules(head) {
-                // Derive a new item for all the symbols in the FIRST set.
-                for &lookahead in &lookaheads {
-                    let mut next_item = Item::new(next_items.len(), rule, lookahead, item.unique);
-                    let mut transition = ItemTransition::new(
-                        (state_id, item.id),
-                        (state_id, next_item.id),
-                        Symbol::Null.id(),
-                    );
-
-                    // If the item derives an existing one, update it accordingly.
-                    if let Some(existing) = buffer.get(&next_item) {
-                        let existing = next_items[existing.id];
-
-                        transition.to.1 = existing.id;
-                        transitions.insert(transition);
-
-                        update_uniqueness(
-                            existing.id,
-                            (state_id, item.id),
-                            &mut next_items,
-                            &transitions,
-                        );
-
-                        continue;
+                let next_item = Item::new(next_items.len(), rule, lookaheads.clone(), unique);
+
+                // If the item derives an existing one, update it accordingly.
+                if let Some(&existing) = buffer.get(&next_item.key()) {
+                    if !is_expanded {
+                        transitions.push(ItemTransition::new(
+                            (state_id, id),
+                            (state_id, existing),
+                            Symbol::Null.id(),
+                        ));
+
+                        children[id].push(existing);
+                        update_uniqueness(existing, id, &mut next_items, &parents, &children);
                     }
 
-                    // If the item is at a nonterminal symbol, add it to the queue.
-                    if next_item.at_nonterminal(&grammar.symbols) {
-                        queue.push_back(next_item.id);
+                    // Unexpanded items are still in the queue.
+                    if next_items[existing].lookaheads.union_with(&lookaheads) && expanded[existing]
+                    {
+                        queue.push_back(existing);
                     }
 
-                    next_items.push(next_item);
-                    transitions.insert(transition);
+                    continue;
+                }
 
-                    // Add both variations to the buffer.
-                    buffer.insert(next_item);
-                    next_item.unique = !next_item.unique;
-                    buffer.insert(next_item);
+                // If the item is at a nonterminal symbol, add it to the queue.
+                if next_item.at_nonterminal(&grammar.symbols) {
+                    queue.push_back(next_item.id);
                 }
+
+                transitions.push(ItemTransition::new(
+                    (state_id, id),
+                    (state_id, next_item.id),
+                    Symbol::Null.id(),
+                ));
+
+                buffer.insert(next_item.key(), next_item.id);
+                children[id].push(next_item.id);
+                parents.push(Some(id));
+                children.push(Vec::new());
+                expanded.push(false);
+                next_items.push(next_item);
             }
         }
 
         // Update items with final unique IDs.
-        let mut next_items: Vec<usize> = next_items
+        let next_items: Vec<usize> = next_items
             .iter()
             .map(|item| {
-                if let Some(existing) = items.get(item) {
-                    return existing.id;
+                if let Some(id) = items.get_index_of(item) {
+                    return id;
                 }
 
                 let id = items.len();
-                let mut item = *item;
+                let mut item = item.clone();
                 item.id = id;
                 items.insert(item);
                 id
@@ -186,8 +204,7 @@ impl State {
             })
             .collect();
 
-        next_items.sort_unstable();
-        Some((State::new(state_id, next_items), transitions))
+        (State::new(state_id, next_items), transitions)
     }
 
     /// Different implementation of the `AsString` trait.
@@ -197,9 +214,9 @@ impl State {
             .iter()
             .enumerate()
             .map(|(idx, &id)| {
-                let mut item = automaton.items[id];
+                let mut item = automaton.items[id].clone();
                 item.id = idx;
-                item.string(&automaton.grammar)
+                item.string(automaton.grammar)
             })
             .collect::<Vec<String>>()
             .join("; ");
@@ -211,29 +228,26 @@ impl State {
 /// Updates the item uniqueness if necessary.
 fn update_uniqueness(
     id: usize,
-    from: (usize, usize),
+    parent: usize,
     items: &mut [Item],
-    transitions: &HashSet<ItemTransition>,
+    parents: &[Option<usize>],
+    children: &[Vec<usize>],
 ) {
     if !items[id].unique {
         return;
     }
 
-    if id == from.1 {
+    if id == parent {
         // If the item derives itself, it's not unique.
         items[id].unique = false;
     } else {
-        // All its parents must be unique and represent the same rule.
-        let parents: Vec<&Item> = transitions
-            .iter()
-            .filter(|transition| transition.to.1 == id)
-            .map(|transition| transition.from.1)
-            .filter_map(|idx| items.get(idx))
-            .collect();
+        // All its parents must be unique and represent the same rule. The existing
+        // parents already are, otherwise the item wouldn't be unique anymore,
+        // so only the new parent is compared with the first one.
+        let first = &items[parents[id].unwrap_or(parent)];
+        let parent = &items[parent];
 
-        items[id].unique = parents
-            .iter()
-            .all(|item| item.unique && item.rule == parents[0].rule && item.dot == parents[0].dot);
+        items[id].unique = parent.unique && parent.rule == first.rule && parent.dot == first.dot;
     }
 
     if items[id].unique {
@@ -241,26 +255,14 @@ fn update_uniqueness(
     }
 
     // Recursively update its derived items.
-    let mut non_unique = HashSet::new();
-    non_unique.insert(id);
-
-    loop {
-        non_unique = transitions
-            .iter()
-            .filter(|transition| {
-                transition.from.0 == from.0
-                    && items[transition.to.1].unique
-                    && non_unique.contains(&transition.from.1)
-            })
-            .map(|transition| transition.to.1)
-            .collect();
+    let mut stack = vec![id];
 
-        if non_unique.is_empty() {
-            break;
-        }
-
-        for &id in &non_unique {
-            items[id].unique = false;
+    while let Some(id) = stack.pop() {
+        for &child in &children[id] {
+            if items[child].unique {
+                items[child].unique = false;
+                stack.push(child);
+            }
         }
     }
 }
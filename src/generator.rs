@@ -0,0 +1,108 @@
+use std::error;
+use std::fmt::{self, Display, Formatter};
+
+use crate::automaton::{self, Automaton, Compact, Data};
+use crate::grammar::Grammar;
+use crate::parser::{self, Tables};
+
+pub mod build;
+mod literal;
+mod rust;
+
+pub use literal::Literal;
+
+/// The `Language` enum describes the language of a generated parser.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Language {
+    /// A Rust module, which depends on the `regex` crate.
+    #[default]
+    Rust,
+}
+
+impl Language {
+    /// Returns the language with the specified name.
+    pub fn from_name(name: &str) -> Option<Language> {
+        match name {
+            "rust" => Some(Self::Rust),
+            _ => None,
+        }
+    }
+}
+
+/// The `Method` enum describes the parsing method of a generated parser.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Method {
+    /// The LL(1) parser of `parser::parse_ll`.
+    Ll,
+
+    /// The LR(1) parser of `parser::parse_lr`.
+    Lr,
+
+    /// The LLLR parser of `parser::parse_lllr`.
+    #[default]
+    Lllr,
+}
+
+impl Method {
+    /// Returns the parsing method with the specified name.
+    pub fn from_name(name: &str) -> Option<Method> {
+        match name {
+            "ll" => Some(Self::Ll),
+            "lr" => Some(Self::Lr),
+            "lllr" => Some(Self::Lllr),
+            _ => None,
+        }
+    }
+}
+
+/// The `ParseTables` enum contains the tables of a parsing method.
+enum ParseTables {
+    Ll(Compact<usize>),
+    Lr(Data),
+    Lllr(Tables),
+}
+
+/// Generates the source of a standalone parser for the grammar, which contains
+/// the lexer, the parse tables and the runtime of the parser. The generated parser
+/// returns the same rules as the parsers in this crate.
+///
+/// The tables of the LLLR parser are constructed on the number of threads.
+pub fn generate(
+    grammar: &Grammar,
+    language: Language,
+    method: Method,
+    threads: usize,
+) -> Result<String, Error> {
+    let tables = match method {
+        Method::Ll => ParseTables::Ll(parser::ll_table(grammar).map_err(Error::Parser)?),
+        Method::Lr => {
+            let automaton = Automaton::new(grammar, 0);
+            ParseTables::Lr(automaton.data().map_err(Error::Automaton)?)
+        }
+        Method::Lllr => {
+            let tables = Tables::new(grammar, threads).map_err(Error::Parser)?;
+            ParseTables::Lllr(tables)
+        }
+    };
+
+    match language {
+        Language::Rust => Ok(rust::generate(grammar, &tables)),
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Automaton(automaton::Error),
+    Parser(parser::Error),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Automaton(error) => write!(f, "{}", error),
+            Self::Parser(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+impl error::Error for Error {}
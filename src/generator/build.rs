@@ -0,0 +1,142 @@
+use std::env;
+use std::error;
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+
+use crate::grammar;
+
+use super::{Language, Method};
+
+/// The `Builder` struct generates a parser from a build script. The grammar is read and
+/// verified, the tables are constructed, and the parser is saved to the `OUT_DIR` directory,
+/// so errors in the grammar fail the build.
+#[derive(Clone, Debug)]
+pub struct Builder {
+    grammar: PathBuf,
+    language: Language,
+    method: Method,
+    threads: usize,
+    output: Option<String>,
+    warnings: bool,
+}
+
+impl Builder {
+    /// Constructs a builder for the grammar file, which generates an LLLR parser in Rust.
+    /// The tables are constructed on all available threads.
+    pub fn new<P: AsRef<Path>>(grammar: P) -> Builder {
+        Builder {
+            grammar: grammar.as_ref().to_path_buf(),
+            language: Language::default(),
+            method: Method::default(),
+            threads: thread::available_parallelism().map_or(1, |threads| threads.get()),
+            output: None,
+            warnings: false,
+        }
+    }
+
+    /// Sets the language of the generated parser.
+    pub fn language(mut self, language: Language) -> Builder {
+        self.language = language;
+        self
+    }
+
+    /// Sets the parsing method of the generated parser.
+    pub fn method(mut self, method: Method) -> Builder {
+        self.method = method;
+        self
+    }
+
+    /// Sets the number of threads used to construct the LLLR tables.
+    pub fn threads(mut self, threads: usize) -> Builder {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// Sets the file name of the generated parser in the `OUT_DIR` directory.
+    /// Defaults to the name of the grammar file with the extension `.rs`.
+    pub fn output(mut self, output: &str) -> Builder {
+        self.output = Some(output.to_owned());
+        self
+    }
+
+    /// Reports the warnings about the token definitions as warnings of the build.
+    pub fn warnings(mut self, warnings: bool) -> Builder {
+        self.warnings = warnings;
+        self
+    }
+
+    /// Generates the parser, and returns the path of the saved file. The build script
+    /// is run again when the grammar file changes.
+    pub fn compile(&self) -> Result<PathBuf, Error> {
+        let filename = self.grammar.display().to_string();
+        println!("cargo:rerun-if-changed={}", filename);
+
+        let directory = env::var_os("OUT_DIR").ok_or(Error::Env("OUT_DIR".to_owned()))?;
+
+        let grammar = grammar::read_file(&self.grammar)
+            .map_err(|error| Error::Read(filename, error.to_string()))?;
+
+        let name = grammar.name.clone();
+        grammar
+            .verify()
+            .map_err(|error| Error::Grammar(name.clone(), error))?;
+
+        if self.warnings {
+            for warning in grammar.analyze_tokens() {
+                println!("cargo:warning=Warning in grammar '{}': {}", name, warning);
+            }
+        }
+
+        let source = super::generate(&grammar, self.language, self.method, self.threads)
+            .map_err(|error| Error::Generate(name, Box::new(error)))?;
+
+        let output = match &self.output {
+            Some(output) => PathBuf::from(output),
+            None => Path::new(self.grammar.file_name().unwrap_or_default()).with_extension("rs"),
+        };
+
+        let path = Path::new(&directory).join(output);
+        fs::write(&path, source)
+            .map_err(|error| Error::Save(path.display().to_string(), error.to_string()))?;
+
+        Ok(path)
+    }
+
+    /// Generates the parser like `compile`, but panics with the error if the parser cannot
+    /// be generated, so cargo reports the failed build script with the error message.
+    pub fn build(&self) -> PathBuf {
+        match self.compile() {
+            Ok(path) => path,
+            Err(error) => panic!("{}", error),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Env(String),
+    Read(String, String),
+    Grammar(String, grammar::Error),
+    Generate(String, Box<super::Error>),
+    Save(String, String),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Env(name) => write!(f, "Environment variable {} is not set", name),
+            Self::Read(filename, error) => {
+                write!(f, "Grammar file '{}' cannot be parsed: {}", filename, error)
+            }
+            Self::Grammar(name, error) => write!(f, "Grammar '{}' is not valid: {}", name, error),
+            Self::Generate(name, error) => write!(f, "Grammar '{}' is not valid: {}", name, error),
+            Self::Save(filename, error) => {
+                write!(f, "Unable to save to file '{}': {}", filename, error)
+            }
+        }
+    }
+}
+
+impl error::Error for Error {}
@@ -0,0 +1,64 @@
+/// The `Literal` trait converts a value to a Rust expression, which constructs
+/// the value in a generated parser. Sequences become references to static arrays.
+pub trait Literal {
+    fn literal(&self) -> String;
+}
+
+impl<T: Literal + ?Sized> Literal for &T {
+    fn literal(&self) -> String {
+        Literal::literal(&**self)
+    }
+}
+
+impl Literal for usize {
+    fn literal(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl Literal for bool {
+    fn literal(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl Literal for str {
+    fn literal(&self) -> String {
+        // The debug format escapes the string the same way as Rust.
+        format!("{:?}", self)
+    }
+}
+
+impl Literal for String {
+    fn literal(&self) -> String {
+        self.as_str().literal()
+    }
+}
+
+impl<T: Literal> Literal for Option<T> {
+    fn literal(&self) -> String {
+        match self {
+            Some(value) => format!("Some({})", value.literal()),
+            None => "None".to_owned(),
+        }
+    }
+}
+
+impl<A: Literal, B: Literal> Literal for (A, B) {
+    fn literal(&self) -> String {
+        format!("({}, {})", self.0.literal(), self.1.literal())
+    }
+}
+
+impl<T: Literal> Literal for [T] {
+    fn literal(&self) -> String {
+        let values: Vec<String> = self.iter().map(Literal::literal).collect();
+        format!("&[{}]", values.join(", "))
+    }
+}
+
+impl<T: Literal> Literal for Vec<T> {
+    fn literal(&self) -> String {
+        self.as_slice().literal()
+    }
+}
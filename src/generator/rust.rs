@@ -0,0 +1,120 @@
+use crate::automaton::Data;
+use crate::grammar::{Grammar, Policy};
+use crate::util;
+
+use super::{Literal, ParseTables};
+
+/// The lexer and the common parts of the parsers.
+const RUNTIME: &str = include_str!("rust/runtime.rs");
+
+/// The tables of the LR automata, which are used by the LR and LLLR parsers.
+const AUTOMATON: &str = include_str!("rust/automaton.rs");
+
+/// The parsers.
+const LL: &str = include_str!("rust/ll.rs");
+const LR: &str = include_str!("rust/lr.rs");
+const LLLR: &str = include_str!("rust/lllr.rs");
+
+/// Returns the source of a Rust module with the parser and its tables.
+pub fn generate(grammar: &Grammar, tables: &ParseTables) -> String {
+    // The LLLR parser also uses the wrapper rules of its derived grammar.
+    let (name, runtime, derived) = match tables {
+        ParseTables::Ll(_) => ("LL(1)", vec![RUNTIME, LL], grammar),
+        ParseTables::Lr(_) => ("LR(1)", vec![RUNTIME, AUTOMATON, LR], grammar),
+        ParseTables::Lllr(tables) => ("LLLR", vec![RUNTIME, AUTOMATON, LLLR], &*tables.grammar),
+    };
+
+    let lexicon = &grammar.lexicon;
+
+    let policy = match lexicon.policy {
+        Policy::Longest => "Longest",
+        Policy::First => "First",
+    };
+
+    let layout = lexicon.layout.map_or("None".to_owned(), |layout| {
+        format!(
+            "Some(Layout {{ indent: {}, dedent: {}, newline: {} }})",
+            layout.indent.literal(),
+            layout.dedent.literal(),
+            layout.newline.literal()
+        )
+    });
+
+    let terminator = lexicon
+        .terminator
+        .as_ref()
+        .map_or("None".to_owned(), |terminator| {
+            format!(
+                "Some(Terminator {{ symbol: {}, after: {}, successors: {} }})",
+                terminator.symbol,
+                util::to_sorted_vec(&terminator.after).literal(),
+                grammar.successors(terminator.symbol).literal()
+            )
+        });
+
+    let mut statics = vec![
+        format!(
+            "pub static SYMBOLS: &[Symbol] = {};",
+            derived.symbols.literal()
+        ),
+        format!("pub static RULES: &[Rule] = {};", derived.rules.literal()),
+        format!(
+            "static MATCHERS: &[(usize, Matcher)] = {};",
+            lexicon.matchers.literal()
+        ),
+        format!("const POLICY: Policy = Policy::{};", policy),
+        format!("static TAB_WIDTH: usize = {};", lexicon.tab_width),
+        format!("static LAYOUT: Option<Layout> = {};", layout),
+        format!("static TERMINATOR: Option<Terminator> = {};", terminator),
+    ];
+
+    match tables {
+        ParseTables::Ll(parse_table) => {
+            let parse_table = parse_table.literal();
+            statics.push(format!(
+                "static PARSE_TABLE: Compact<usize> = {};",
+                parse_table
+            ));
+        }
+        ParseTables::Lr(data) => {
+            statics.push(format!("static DATA: Data = {};", data.literal()));
+        }
+        ParseTables::Lllr(tables) => {
+            let parse_table = tables.parse_table.literal();
+            statics.push(format!(
+                "static PARSE_TABLE: Compact<usize> = {};",
+                parse_table
+            ));
+
+            // The embedded tables are sorted by their wrapper rules,
+            // and the wrappers refer to them by their index.
+            let rules = util::to_sorted_vec(tables.data.keys());
+
+            let wrappers = (tables.wrappers.iter())
+                .map(|(&position, rule)| (position, rules.binary_search(&rule).unwrap()));
+
+            let wrappers = util::to_sorted_vec(wrappers).literal();
+            statics.push(format!(
+                "static WRAPPERS: &[((usize, usize), usize)] = {};",
+                wrappers
+            ));
+
+            let data: Vec<&Data> = rules.iter().map(|rule| &tables.data[rule]).collect();
+            statics.push(format!("static DATA: &[Data] = {};", data.literal()));
+        }
+    }
+
+    format!(
+        "// {} parser for the grammar '{}', generated by syn {}. Do not edit.\n\
+         //\n\
+         // The parser depends on the `regex` crate.\n\n\
+         {}\n\
+         // The tables.\n\n\
+         {}\n",
+        name,
+        grammar.name,
+        env!("CARGO_PKG_VERSION"),
+        runtime.join("\n"),
+        statics.join("\n\n")
+    )
+}
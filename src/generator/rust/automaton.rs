@@ -0,0 +1,37 @@
+// The tables of the LR automata.
+
+/// The `Action` enum in the ACTION table.
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug)]
+enum Action {
+    Shift(usize),
+    Reduce(usize),
+    Accept(usize),
+}
+
+/// The `Data` struct contains the tables of an LR automaton.
+#[allow(dead_code)]
+struct Data {
+    start_rule: usize,
+    // The rule and the dot of the items in the BACKTRACK table, sorted by their IDs.
+    items: &'static [(usize, (usize, usize))],
+    action_table: Compact<Action>,
+    goto_table: Compact<usize>,
+    left_table: Compact<usize>,
+    backtrack_table: Compact<(usize, usize)>,
+}
+
+/// Removes the rule symbols from the stack.
+fn reduce_stack(stack: &mut Vec<(usize, usize)>, symbols: &[usize]) -> Result<(), Error> {
+    for &id in symbols.iter().rev() {
+        if id == NULL {
+            continue;
+        }
+
+        if stack.pop().filter(|&(symbol, _)| symbol == id).is_none() {
+            return Err(Error::Internal);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,43 @@
+// The LL(1) parser.
+
+/// Parses the tokens, which are returned by `tokenize`, and returns the list of rules.
+pub fn parse_tokens(tokens: &[Token]) -> Result<Vec<usize>, Error> {
+    let mut rules = Vec::new();
+    let mut stack = vec![START];
+    let mut input = Input::new(tokens);
+
+    while !stack.is_empty() && !input.is_empty() {
+        let &symbol = stack.last().unwrap();
+        let lookahead = input.symbol();
+
+        if let Some(rule) = PARSE_TABLE.get(symbol, lookahead) {
+            rules.push(rule);
+            stack.pop();
+
+            for &symbol in RULES[rule].body.iter().rev() {
+                if symbol != NULL {
+                    stack.push(symbol);
+                }
+            }
+
+            continue;
+        }
+
+        if symbol != lookahead {
+            break;
+        }
+
+        stack.pop();
+        input.pop();
+    }
+
+    if !stack.is_empty() {
+        return Err(Error::Eof);
+    }
+
+    if let Some(token) = next_token(&mut input) {
+        return Err(Error::Parse(Box::new(token)));
+    }
+
+    Ok(rules)
+}
@@ -0,0 +1,181 @@
+// The LLLR parser, which embeds LR parsers for the symbols with LL(1) conflicts.
+
+/// Parses the tokens, which are returned by `tokenize`, and returns the list of rules.
+pub fn parse_tokens(tokens: &[Token]) -> Result<Vec<usize>, Error> {
+    let mut rules = Vec::new();
+    let mut stack = vec![(START, (0, 0))];
+    let mut input = Input::new(tokens);
+
+    while !stack.is_empty() && !input.is_empty() {
+        let (symbol, position) = *stack.last().unwrap();
+        let lookahead = input.symbol();
+
+        if let Some(rule) = PARSE_TABLE.get(symbol, lookahead) {
+            rules.push(rule);
+            stack.pop();
+
+            for (idx, &symbol) in RULES[rule].body.iter().enumerate().rev() {
+                if symbol != NULL {
+                    stack.push((symbol, (rule, idx)));
+                }
+            }
+
+            continue;
+        }
+
+        // Start the LR parser if necessary.
+        if let Ok(idx) = WRAPPERS.binary_search_by_key(&position, |&(position, _)| position) {
+            let data = &DATA[WRAPPERS[idx].1];
+
+            let mut lr_rules = vec![Vec::new()];
+            let mut lr_stack = vec![(symbol, 0)];
+            input.push_end();
+
+            let is_valid = loop {
+                if lr_stack.is_empty() {
+                    break false;
+                }
+
+                let state = lr_stack.last().unwrap().1;
+                let lookahead = input.symbol();
+
+                // Check if the LR parser can stop.
+                if let Some(((rule, dot), tail)) = find_unique_item(data, state, lookahead) {
+                    reduce_rules(&mut lr_rules, dot);
+                    lr_rules.last_mut().unwrap().insert(0, rule);
+
+                    let count = lr_rules.len() - 1;
+                    reduce_rules(&mut lr_rules, count);
+
+                    // Replace embedded parser symbols on the LL stack.
+                    let body = &RULES[data.start_rule].body[1..];
+                    stack.truncate(stack.len() - body.len());
+                    stack.extend(tail.iter().rev());
+
+                    break true;
+                }
+
+                let action = match data.action_table.get(state, lookahead) {
+                    Some(action) => action,
+                    None => break false,
+                };
+
+                match action {
+                    Action::Shift(state) => {
+                        lr_stack.push((lookahead, state));
+                        lr_rules.push(Vec::new());
+                        input.pop();
+                    }
+                    Action::Reduce(rule) => {
+                        let body = RULES[rule].body;
+                        reduce_stack(&mut lr_stack, body)?;
+                        reduce_rules(&mut lr_rules, body.len());
+                        lr_rules.last_mut().unwrap().insert(0, rule);
+                        lr_rules.push(Vec::new());
+
+                        let state = lr_stack.last().unwrap().1;
+                        let head = RULES[rule].head;
+
+                        match data.goto_table.get(state, head) {
+                            Some(next_state) => lr_stack.push((head, next_state)),
+                            None => return Err(Error::Internal),
+                        }
+                    }
+                    Action::Accept(rule) => {
+                        let body = &RULES[rule].body[1..];
+                        reduce_stack(&mut lr_stack, body)?;
+                        reduce_rules(&mut lr_rules, body.len());
+                        stack.truncate(stack.len() - body.len());
+
+                        break true;
+                    }
+                }
+            };
+
+            if !is_valid {
+                return match next_token(&mut input) {
+                    Some(token) => Err(Error::Parse(Box::new(token))),
+                    None => Err(Error::Eof),
+                };
+            }
+
+            rules.extend(lr_rules.last().unwrap());
+            continue;
+        }
+
+        if symbol != lookahead {
+            break;
+        }
+
+        stack.pop();
+        input.pop();
+    }
+
+    if !stack.is_empty() {
+        return Err(Error::Eof);
+    }
+
+    if let Some(token) = next_token(&mut input) {
+        return Err(Error::Parse(Box::new(token)));
+    }
+
+    Ok(rules)
+}
+
+/// The symbols and their positions remaining in the automaton.
+type Tail = Vec<(usize, (usize, usize))>;
+
+/// Finds a unique item in the automaton state.
+/// Returns the rule and dot of the item, and the remaining symbols in the automaton.
+fn find_unique_item(data: &Data, state: usize, lookahead: usize) -> Option<((usize, usize), Tail)> {
+    if let Some(Action::Accept(_)) = data.action_table.get(state, lookahead) {
+        return None;
+    }
+
+    if SYMBOLS[lookahead].kind == Kind::Internal {
+        return None;
+    }
+
+    // Find the unique item.
+    let mut from = (state, data.left_table.get(state, lookahead)?);
+    let item = find_item(data, from.1);
+    let mut tail = positions(item.0, item.1);
+    let mut current_rule = item.0;
+
+    // Follow item transitions to find the remaining symbols.
+    while let Some(to) = data.backtrack_table.get(from.0, from.1) {
+        from = to;
+        let (rule, dot) = find_item(data, from.1);
+
+        if rule != current_rule {
+            tail.extend(positions(rule, dot + 1));
+            current_rule = rule;
+        }
+    }
+
+    Some((item, tail))
+}
+
+/// Returns the rule and dot of the item in the automaton.
+fn find_item(data: &Data, id: usize) -> (usize, usize) {
+    let idx = data.items.binary_search_by_key(&id, |&(id, _)| id).unwrap();
+    data.items[idx].1
+}
+
+/// Returns the symbols of the rule from the index, with their positions.
+fn positions(rule: usize, idx: usize) -> Tail {
+    let body = RULES[rule].body;
+    (idx..body.len()).map(|idx| (body[idx], (rule, idx))).collect()
+}
+
+/// Merges the left-parse rules.
+fn reduce_rules(rules: &mut Vec<Vec<usize>>, count: usize) {
+    let mut buffer = Vec::new();
+
+    for _ in 0..count {
+        let rules = rules.pop().unwrap();
+        buffer = [rules, buffer].concat();
+    }
+
+    rules.last_mut().unwrap().extend(buffer);
+}
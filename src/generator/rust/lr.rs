@@ -0,0 +1,62 @@
+// The LR(1) parser.
+
+/// Parses the tokens, which are returned by `tokenize`, and returns the list of rules.
+pub fn parse_tokens(tokens: &[Token]) -> Result<Vec<usize>, Error> {
+    let mut rules = Vec::new();
+    let mut stack = vec![(START, 0)];
+    let mut input = Input::new(tokens);
+
+    let is_valid = loop {
+        if stack.is_empty() {
+            break false;
+        }
+
+        let state = stack.last().unwrap().1;
+        let lookahead = input.symbol();
+
+        let action = match DATA.action_table.get(state, lookahead) {
+            Some(action) => action,
+            None => break false,
+        };
+
+        match action {
+            Action::Shift(state) => {
+                stack.push((lookahead, state));
+                input.pop();
+            }
+            Action::Reduce(rule) => {
+                reduce_stack(&mut stack, RULES[rule].body)?;
+                rules.push(rule);
+
+                let state = stack.last().unwrap().1;
+                let head = RULES[rule].head;
+
+                match DATA.goto_table.get(state, head) {
+                    Some(next_state) => stack.push((head, next_state)),
+                    None => return Err(Error::Internal),
+                }
+            }
+            Action::Accept(rule) => {
+                reduce_stack(&mut stack, RULES[rule].body)?;
+                reduce_stack(&mut stack, &[RULES[rule].head])?;
+                rules.push(rule);
+
+                break true;
+            }
+        }
+    };
+
+    if !stack.is_empty() {
+        return Err(Error::Internal);
+    }
+
+    if !is_valid {
+        return match next_token(&mut input) {
+            Some(token) => Err(Error::Parse(Box::new(token))),
+            None => Err(Error::Eof),
+        };
+    }
+
+    rules.reverse();
+    Ok(rules)
+}
@@ -0,0 +1,658 @@
+// The lexer and the common parts of the parsers. The tables are at the end of the module.
+
+use std::error;
+use std::fmt::{self, Display, Formatter};
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+/// The IDs of the internal symbols.
+const START: usize = 0;
+const END: usize = 1;
+const NULL: usize = 2;
+const ERROR: usize = 3;
+
+/// Marks an empty row of a parse table, or a slot that isn't used by any row.
+const EMPTY: usize = usize::MAX;
+
+/// The `Kind` enum describes the kind of a grammar symbol.
+#[allow(dead_code)]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Kind {
+    Internal,
+    NonTerminal,
+    Terminal,
+    Ignored,
+}
+
+/// The `Symbol` struct describes a grammar symbol.
+#[derive(Clone, Copy, Debug)]
+pub struct Symbol {
+    pub name: &'static str,
+    pub kind: Kind,
+}
+
+/// The `Rule` struct describes a grammar rule.
+#[derive(Clone, Copy, Debug)]
+pub struct Rule {
+    pub head: usize,
+    pub body: &'static [usize],
+}
+
+/// Returns the rule in the same format as the `syn` command.
+pub fn rule_string(id: usize) -> String {
+    let rule = &RULES[id];
+    let body: Vec<&str> = rule.body.iter().map(|&id| SYMBOLS[id].name).collect();
+    format!("({}) {} → {}", id, SYMBOLS[rule.head].name, body.join(" "))
+}
+
+/// The `Span` struct denotes the location of a token, where the end position
+/// is the last character of the token.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Span {
+    pub start: (usize, usize),
+    pub end: (usize, usize),
+}
+
+impl Span {
+    fn new(position: (usize, usize)) -> Span {
+        Span {
+            start: position,
+            end: position,
+        }
+    }
+}
+
+impl Display for Span {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        let Span { start, end } = self;
+
+        if start.0 == end.0 {
+            if start.1 == end.1 {
+                return write!(f, "{}:{}", start.0, start.1);
+            }
+
+            return write!(f, "{}:{}-{}", start.0, start.1, end.1);
+        }
+
+        write!(f, "{}:{}-{}:{}", start.0, start.1, end.0, end.1)
+    }
+}
+
+/// The `Token` struct describes an element in the input. Synthetic tokens
+/// are inserted by the lexer, and don't correspond to any text in the input.
+#[derive(Clone, Debug)]
+pub struct Token {
+    pub symbol: usize,
+    pub lexeme: String,
+    pub span: Span,
+    pub synthetic: bool,
+}
+
+impl Token {
+    fn synthetic(symbol: usize, span: Span) -> Token {
+        Token {
+            symbol,
+            lexeme: String::new(),
+            span,
+            synthetic: true,
+        }
+    }
+
+    fn end(span: Span) -> Token {
+        Token {
+            symbol: END,
+            lexeme: SYMBOLS[END].name.to_owned(),
+            span,
+            synthetic: true,
+        }
+    }
+
+    /// Returns an empty span at the position following the token.
+    fn span_after(&self) -> Span {
+        let position = advance(self.span.end, self.lexeme.chars().last().unwrap());
+        Span::new(position)
+    }
+}
+
+impl Display for Token {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        write!(f, "{} @ {} [{}]", self.lexeme, self.span, self.symbol)
+    }
+}
+
+/// The `Matcher` enum describes a method to match the text of a token.
+#[allow(dead_code)]
+enum Matcher {
+    Regex(&'static str),
+    Text(&'static str),
+    Group(&'static [&'static str]),
+    // Each character of a string is given by the characters with the same case folding.
+    Folded(&'static [&'static [&'static str]]),
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Match {
+    None,
+    Partial,
+    Full,
+}
+
+/// The `Policy` enum describes how the lexer chooses between tokens matching the same input.
+#[allow(dead_code)]
+#[derive(PartialEq)]
+enum Policy {
+    Longest,
+    First,
+}
+
+/// The `Layout` struct describes the synthetic symbols of an indentation-sensitive grammar.
+#[allow(dead_code)]
+struct Layout {
+    indent: Option<usize>,
+    dedent: Option<usize>,
+    newline: Option<usize>,
+}
+
+/// The `Terminator` struct describes a terminal symbol, which is inserted
+/// at a line break if the line ends with one of the `after` symbols.
+#[allow(dead_code)]
+struct Terminator {
+    symbol: usize,
+    after: &'static [usize],
+    successors: &'static [usize],
+}
+
+/// Returns the compiled regular expressions of the matchers.
+fn regexes() -> &'static [Option<Regex>] {
+    static REGEXES: OnceLock<Vec<Option<Regex>>> = OnceLock::new();
+
+    REGEXES.get_or_init(|| {
+        (MATCHERS.iter())
+            .map(|(_, matcher)| match matcher {
+                Matcher::Regex(pattern) => Some(Regex::new(pattern).unwrap()),
+                _ => None,
+            })
+            .collect()
+    })
+}
+
+/// Matches the text against the matcher with the index.
+fn match_str(idx: usize, text: &str) -> Match {
+    match &MATCHERS[idx].1 {
+        Matcher::Regex(_) => {
+            let regex = regexes()[idx].as_ref().unwrap();
+
+            let captures = match regex.captures(text) {
+                Some(captures) => captures,
+                None => return Match::None,
+            };
+
+            // The last capture group should not be empty.
+            match captures.get(captures.len() - 1) {
+                Some(group) if !group.as_str().is_empty() => Match::Full,
+                _ => Match::Partial,
+            }
+        }
+        Matcher::Text(string) => match_group(&[*string], text),
+        Matcher::Group(strings) => match_group(strings, text),
+        Matcher::Folded(strings) => {
+            let chars: Vec<char> = text.chars().collect();
+
+            for string in strings.iter() {
+                let is_prefix = chars.len() <= string.len()
+                    && (chars.iter().zip(string.iter())).all(|(&ch, folded)| folded.contains(ch));
+
+                if is_prefix && chars.len() == string.len() {
+                    return Match::Full;
+                }
+
+                if is_prefix {
+                    return Match::Partial;
+                }
+            }
+
+            Match::None
+        }
+    }
+}
+
+/// Compares the text to a group of strings.
+fn match_group(strings: &[&str], text: &str) -> Match {
+    for string in strings {
+        if *string == text {
+            return Match::Full;
+        }
+
+        if string.starts_with(text) {
+            return Match::Partial;
+        }
+    }
+
+    Match::None
+}
+
+/// Returns the symbol of the first full match in order of precedence,
+/// otherwise the symbol of the first partial match.
+fn find_symbol(text: &str) -> Option<(usize, bool)> {
+    let mut symbol = None;
+
+    for (idx, &(id, _)) in MATCHERS.iter().enumerate() {
+        match match_str(idx, text) {
+            Match::Full => return Some((id, true)),
+            Match::Partial if symbol.is_none() => symbol = Some((id, false)),
+            _ => {}
+        }
+    }
+
+    symbol
+}
+
+/// Advances the position based on the character.
+fn advance(position: (usize, usize), ch: char) -> (usize, usize) {
+    let (row, column) = position;
+
+    match ch {
+        '\n' => (row + 1, 1),
+        '\t' => (row, column + TAB_WIDTH - (column - 1) % TAB_WIDTH),
+        _ => (row, column + 1),
+    }
+}
+
+/// Returns the symbol, the length in characters and the span of the token
+/// at the start of the source, according to the matching policy.
+fn find_token(source: &[char], position: (usize, usize)) -> Result<(usize, usize, Span), Error> {
+    let mut text = String::new();
+    let mut span = Span::new(position);
+
+    if POLICY == Policy::First {
+        for (idx, &(id, _)) in MATCHERS.iter().enumerate() {
+            let mut length = None;
+
+            for (end, &ch) in source.iter().enumerate() {
+                text.push(ch);
+
+                match match_str(idx, &text) {
+                    Match::Full => length = Some(end + 1),
+                    Match::Partial => {}
+                    Match::None => break,
+                }
+            }
+
+            text.clear();
+
+            if let Some(length) = length {
+                for &ch in &source[..length - 1] {
+                    span.end = advance(span.end, ch);
+                }
+
+                return Ok((id, length, span));
+            }
+        }
+
+        return Err(Error::Token(source[0].to_string(), span));
+    }
+
+    let mut last_match = None;
+
+    for (idx, &ch) in source.iter().enumerate() {
+        text.push(ch);
+
+        // Stop at the first character without at least a partial match.
+        let (id, is_full_match) = match find_symbol(&text) {
+            Some(current_match) => current_match,
+            None => break,
+        };
+
+        if is_full_match {
+            last_match = Some((id, idx + 1, span));
+        }
+
+        span.end = advance(span.end, ch);
+    }
+
+    last_match.ok_or(Error::Token(text, span))
+}
+
+/// Returns the error for the unrecognized characters at the start of the source,
+/// which end before the next character that starts a valid token.
+fn find_error(source: &[char], position: (usize, usize)) -> Error {
+    let mut text = String::new();
+    let mut span = Span::new(position);
+
+    for (idx, &ch) in source.iter().enumerate() {
+        if idx > 0 {
+            let next = advance(span.end, source[idx - 1]);
+
+            if find_token(&source[idx..], next).is_ok() {
+                break;
+            }
+
+            span.end = next;
+        }
+
+        text.push(ch);
+    }
+
+    Error::Token(text, span)
+}
+
+/// Returns the tokens of the text, which end with the $ symbol. The ignored tokens are
+/// removed, and the terminators and the layout symbols are inserted.
+pub fn tokenize(text: &str) -> Result<Vec<Token>, Error> {
+    let source: Vec<char> = text.chars().collect();
+    let mut terminator = TerminatorState::new();
+    let mut layout = LayoutState::new();
+    let mut tokens = Vec::new();
+    let mut position = (1, 1);
+    let mut idx = 0;
+
+    while idx < source.len() {
+        let (symbol, length, span) = find_token(&source[idx..], position)
+            .map_err(|_| find_error(&source[idx..], position))?;
+
+        let token = Token {
+            symbol,
+            lexeme: source[idx..idx + length].iter().collect(),
+            span,
+            synthetic: false,
+        };
+
+        position = token.span_after().start;
+        idx += length;
+
+        let mut staged = Vec::new();
+        terminator.push(token, &mut staged);
+
+        for token in staged {
+            layout.push(token, &mut tokens)?;
+        }
+    }
+
+    let mut staged = Vec::new();
+    terminator.finish(&mut staged);
+
+    for token in staged {
+        layout.push(token, &mut tokens)?;
+    }
+
+    let end = Span::new(position);
+    layout.finish(end, &mut tokens);
+
+    tokens.retain(|token| SYMBOLS[token.symbol].kind != Kind::Ignored);
+    tokens.push(Token::end(end));
+
+    Ok(tokens)
+}
+
+/// Inserts the terminator tokens at line breaks.
+struct TerminatorState {
+    line_break: bool,
+    last_token: Option<(usize, Span)>,
+}
+
+impl TerminatorState {
+    fn new() -> TerminatorState {
+        TerminatorState {
+            line_break: false,
+            last_token: None,
+        }
+    }
+
+    fn push(&mut self, token: Token, output: &mut Vec<Token>) {
+        let terminator = match &TERMINATOR {
+            Some(terminator) => terminator,
+            None => return output.push(token),
+        };
+
+        if SYMBOLS[token.symbol].kind == Kind::Ignored {
+            self.line_break |= token.lexeme.contains('\n');
+            output.push(token);
+            return;
+        }
+
+        if self.line_break && terminator.successors.contains(&token.symbol) {
+            self.insert(terminator, output);
+        }
+
+        self.line_break = false;
+        self.last_token = Some((token.symbol, token.span_after()));
+        output.push(token);
+    }
+
+    fn finish(&mut self, output: &mut Vec<Token>) {
+        // The end of the file also ends the last line.
+        if let Some(terminator) = &TERMINATOR {
+            if terminator.successors.contains(&END) {
+                self.insert(terminator, output);
+            }
+        }
+    }
+
+    fn insert(&self, terminator: &Terminator, output: &mut Vec<Token>) {
+        if let Some((symbol, span)) = self.last_token {
+            if terminator.after.contains(&symbol) {
+                output.push(Token::synthetic(terminator.symbol, span));
+            }
+        }
+    }
+}
+
+/// Inserts the INDENT, DEDENT and NEWLINE tokens.
+struct LayoutState {
+    levels: Vec<usize>,
+    depth: usize,
+    line_break: bool,
+    last_span: Option<Span>,
+}
+
+impl LayoutState {
+    fn new() -> LayoutState {
+        LayoutState {
+            levels: vec![1],
+            depth: 0,
+            line_break: true,
+            last_span: None,
+        }
+    }
+
+    fn push(&mut self, token: Token, output: &mut Vec<Token>) -> Result<(), Error> {
+        let layout = match &LAYOUT {
+            Some(layout) => layout,
+            None => {
+                output.push(token);
+                return Ok(());
+            }
+        };
+
+        if SYMBOLS[token.symbol].kind == Kind::Ignored {
+            self.line_break |= token.lexeme.contains('\n');
+            output.push(token);
+            return Ok(());
+        }
+
+        // Inserted terminators don't start a logical line.
+        if token.synthetic {
+            output.push(token);
+            return Ok(());
+        }
+
+        if self.line_break && self.depth == 0 {
+            let span = Span::new(token.span.start);
+            let column = span.start.1;
+
+            if let Some(last_span) = self.last_span {
+                push(output, layout.newline, last_span);
+            }
+
+            if column > *self.levels.last().unwrap() {
+                self.levels.push(column);
+                push(output, layout.indent, span);
+            }
+
+            while column < *self.levels.last().unwrap() {
+                self.levels.pop();
+                push(output, layout.dedent, span);
+            }
+
+            // The column should match an enclosing block.
+            if column != *self.levels.last().unwrap() {
+                return Err(Error::Indent(span));
+            }
+        }
+
+        match token.lexeme.as_str() {
+            "(" | "[" | "{" => self.depth += 1,
+            ")" | "]" | "}" => self.depth = self.depth.saturating_sub(1),
+            _ => {}
+        }
+
+        self.line_break = false;
+        self.last_span = Some(token.span_after());
+        output.push(token);
+        Ok(())
+    }
+
+    fn finish(&mut self, end: Span, output: &mut Vec<Token>) {
+        if let Some(layout) = &LAYOUT {
+            if let Some(last_span) = self.last_span {
+                push(output, layout.newline, last_span);
+            }
+
+            for _ in 1..self.levels.len() {
+                push(output, layout.dedent, end);
+            }
+        }
+    }
+}
+
+/// Pushes a synthetic token if the grammar uses its symbol.
+fn push(tokens: &mut Vec<Token>, symbol: Option<usize>, span: Span) {
+    if let Some(symbol) = symbol {
+        tokens.push(Token::synthetic(symbol, span));
+    }
+}
+
+/// The `Compact` struct is a parse table compressed with row displacement.
+struct Compact<T: 'static> {
+    columns: &'static [usize],
+    offsets: &'static [usize],
+    checks: &'static [usize],
+    values: &'static [T],
+    defaults: &'static [Option<T>],
+}
+
+impl<T: Copy> Compact<T> {
+    /// Returns the value in the row and column, or the default value of the row.
+    fn get(&self, row: usize, column: usize) -> Option<T> {
+        let offset = self.offsets.get(row).copied().unwrap_or(EMPTY);
+        let column = self.columns.get(column).copied().unwrap_or(EMPTY);
+
+        match offset.checked_add(column) {
+            Some(idx) if self.checks.get(idx) == Some(&offset) => Some(self.values[idx]),
+            _ => self.defaults.get(row).copied().flatten(),
+        }
+    }
+}
+
+/// Parses the text and returns the list of rules.
+pub fn parse(text: &str) -> Result<Vec<usize>, Error> {
+    parse_tokens(&tokenize(text)?)
+}
+
+/// The `Input` struct walks the parser input by index. The input starts with the $ symbol,
+/// and ends with it unless the tokens already do. The LLLR parser pushes additional
+/// $ symbols in front of the next token.
+struct Input<'a> {
+    tokens: &'a [Token],
+    end: Token,
+    idx: usize,
+    len: usize,
+    ends: usize,
+}
+
+impl<'a> Input<'a> {
+    fn new(tokens: &'a [Token]) -> Input<'a> {
+        let is_closed = tokens.last().is_some_and(|token| token.symbol == END);
+        let len = tokens.len() + if is_closed { 1 } else { 2 };
+
+        Input {
+            tokens,
+            end: Token::end(Span::default()),
+            idx: 0,
+            len,
+            ends: 0,
+        }
+    }
+
+    #[allow(dead_code)]
+    fn is_empty(&self) -> bool {
+        self.ends == 0 && self.idx == self.len
+    }
+
+    fn front(&self) -> Option<&Token> {
+        if self.ends > 0 || self.idx == 0 {
+            return Some(&self.end);
+        }
+
+        match self.tokens.get(self.idx - 1) {
+            Some(token) => Some(token),
+            None if self.idx < self.len => Some(&self.end),
+            None => None,
+        }
+    }
+
+    fn symbol(&self) -> usize {
+        self.front().map_or(NULL, |token| token.symbol)
+    }
+
+    fn pop(&mut self) {
+        if self.ends > 0 {
+            self.ends -= 1;
+        } else if self.idx < self.len {
+            self.idx += 1;
+        }
+    }
+
+    #[allow(dead_code)]
+    fn push_end(&mut self) {
+        self.ends += 1;
+    }
+}
+
+/// Returns the next input token, ignoring internal symbols other than error tokens.
+fn next_token(input: &mut Input) -> Option<Token> {
+    let token = (input.front())
+        .filter(|token| token.symbol == ERROR || SYMBOLS[token.symbol].kind != Kind::Internal)
+        .cloned();
+
+    input.pop();
+    token
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Indent(Span),
+    Token(String, Span),
+    Eof,
+    #[allow(dead_code)]
+    Internal,
+    Parse(Box<Token>),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Indent(span) => write!(f, "Inconsistent indentation @ {}", span),
+            Self::Token(lexeme, span) => {
+                let lexeme = lexeme.escape_default();
+                write!(f, "Cannot recognize token '{}' @ {}", lexeme, span)
+            }
+            Self::Eof => write!(f, "Unexpected end of file"),
+            Self::Internal => write!(f, "Internal error"),
+            Self::Parse(token) => write!(f, "Unexpected token {}", token),
+        }
+    }
+}
+
+impl error::Error for Error {}
@@ -1,38 +1,54 @@
-use std::cell::RefCell;
 use std::collections::{HashMap, HashSet};
 use std::error;
 use std::fmt::{self, Display, Formatter};
 
 use crate::automaton::Action;
-use crate::util;
+use crate::util::encoding::{self, Encoder};
+use crate::util::{self, BitSet};
 
+mod analysis;
+mod derived;
+mod lexicon;
 mod matcher;
 mod reader;
 mod rule;
 mod symbol;
 
-use matcher::{Match, Matcher};
+pub use analysis::Warning;
+pub use derived::Derived;
+pub use lexicon::{Layout, Lexicon, Policy, Terminator, ValueKind};
+pub use matcher::Match;
 pub use reader::read_file;
 pub use rule::Rule;
 pub use symbol::Symbol;
 
+// The grammar must be shareable between parser threads.
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<Grammar>();
+};
+
 /// The `Position` type represents a symbol position in the grammar.
 pub type Position = (usize, usize);
 
 /// The `Grammar` struct describes a grammar to parse the input file with.
+///
+/// The grammar is immutable after construction, and it's `Send` and `Sync`, so it can be
+/// shared between threads. The LLLR parser adds its wrapper rules to a `Derived` grammar.
 #[derive(Clone, Debug)]
 pub struct Grammar {
     pub name: String,
     description: String,
     pub symbols: Vec<Symbol>,
-    matchers: Vec<(usize, Matcher)>,
+    pub lexicon: Lexicon,
     start_symbol: usize,
     pub rules: Vec<Rule>,
     pub actions: HashMap<usize, Action>,
     // List of rules for a specific symbol.
     symbol_rules: HashMap<usize, Vec<usize>>,
-    first: RefCell<HashMap<usize, Vec<usize>>>,
-    follow: RefCell<HashMap<usize, Vec<usize>>>,
+    // FIRST and FOLLOW sets indexed by the symbol ID.
+    first: Vec<BitSet>,
+    follow: Vec<BitSet>,
 }
 
 impl Grammar {
@@ -41,7 +57,7 @@ impl Grammar {
         name: String,
         description: String,
         symbols: Vec<Symbol>,
-        matchers: Vec<(usize, Matcher)>,
+        lexicon: Lexicon,
         start_symbol: usize,
         rules: Vec<Rule>,
         actions: HashMap<usize, Action>,
@@ -55,18 +71,21 @@ impl Grammar {
                 acc
             });
 
-        Grammar {
+        let mut grammar = Grammar {
             name,
             description,
             symbols,
-            matchers,
+            lexicon,
             start_symbol,
             rules,
             actions,
             symbol_rules,
-            first: RefCell::new(HashMap::new()),
-            follow: RefCell::new(HashMap::new()),
-        }
+            first: Vec::new(),
+            follow: Vec::new(),
+        };
+
+        grammar.compute_sets();
+        grammar
     }
 
     /// Returns the symbol with the specified ID.
@@ -87,6 +106,23 @@ impl Grammar {
             .collect()
     }
 
+    /// Returns a hash of the symbols, rules and actions, which the parse tables are constructed
+    /// from. The hash changes when the grammar is edited, and doesn't depend on the platform.
+    pub fn content_hash(&self) -> u64 {
+        let mut encoder = Encoder::new();
+        encoder.write(&self.symbols);
+        encoder.write(&self.rules);
+        encoder.write(&self.start_symbol);
+        // The actions are written in the order of their symbols.
+        encoder.write(&self.actions);
+        encoding::hash(&encoder.into_bytes())
+    }
+
+    /// Returns the warnings about overlapping, unreachable and empty token definitions.
+    pub fn analyze_tokens(&self) -> Vec<Warning> {
+        analysis::analyze(self)
+    }
+
     /// Verifies if the grammar is valid.
     pub fn verify(&self) -> Result<(), Error> {
         if !self.symbol_rules.contains_key(&self.start_symbol) {
@@ -166,243 +202,200 @@ impl Grammar {
 
     /// Returns the FIRST set of the specified symbol.
     pub fn first(&self, symbol: usize) -> Vec<usize> {
-        if let Some(first) = self.first.borrow().get(&symbol) {
-            return first.clone();
-        }
+        self.first[symbol].to_vec()
+    }
 
-        let mut first = HashSet::new();
-        let null = Symbol::Null.id();
+    /// Returns the FOLLOW set of the specified nonterminal symbol.
+    pub fn follow(&self, symbol: usize) -> Vec<usize> {
+        self.follow[symbol].to_vec()
+    }
 
-        // If the symbol is a terminal, it's the only member of the FIRST set.
-        if self.symbol(symbol).is_terminal() {
-            first.insert(symbol);
-            return self.cache_first(symbol, &first);
-        }
+    /// Returns the FIRST set of a sequence of symbols.
+    pub fn first_sequence(&self, symbols: &[usize]) -> Vec<usize> {
+        first_of(&self.first, symbols).to_vec()
+    }
 
-        if !self.symbol_rules.contains_key(&symbol) {
-            return Vec::new();
-        }
+    /// Returns the FIRST set of a sequence of symbols,
+    /// followed by any of the lookahead symbols.
+    pub fn first_lookahead_set(&self, symbols: &[usize], lookaheads: &BitSet) -> BitSet {
+        let null = Symbol::Null.id();
+        let mut result = first_of(&self.first, symbols);
 
-        // Find FIRST set of all rules of the specified symbol (s).
-        // Start with the first symbol of each rule (h).
-        let mut rules: Vec<(&Rule, usize)> = self.symbol_rules[&symbol]
+        if symbols
             .iter()
-            .map(|&id| (self.rule(id), 0))
-            .collect();
+            .all(|&symbol| self.first[symbol].contains(null))
+        {
+            result.remove(null);
 
-        loop {
-            for (rule, idx) in &mut rules {
-                let mut rule_first = HashSet::new();
-
-                for &id in rule.tail(*idx) {
-                    *idx += 1;
-
-                    // If the nonterminal symbol is the rule head itself,
-                    // skip it until we can find the partial FIRST set.
-                    if id == symbol {
-                        rule_first.remove(&null);
-                        break;
-                    }
-
-                    let first = self.first(id);
-                    let has_null = first.contains(&null);
-                    rule_first.extend(first);
-
-                    // If FIRST(h) does not contain ϵ, remove it from FIRST(s).
-                    if !has_null {
-                        rule_first.remove(&null);
-                        *idx = rule.body.len();
-                        break;
-                    }
-                }
-
-                // Cache current result to prevent an infinite loop.
-                first.extend(rule_first);
-                self.cache_first(symbol, &first);
-            }
-
-            // If all symbols have been checked, or FIRST(s) does not contain ϵ, we're done.
-            let all_done = rules.iter().all(|&(rule, idx)| rule.body.len() == idx);
-            let has_null = first.contains(&null);
-
-            if all_done || !has_null {
-                break;
+            for symbol in lookaheads.iter() {
+                result.union_with(&self.first[symbol]);
             }
         }
 
-        self.cache_first(symbol, &first)
+        result
     }
 
-    /// Returns the FOLLOW set of the specified nonterminal symbol.
-    pub fn follow(&self, symbol: usize) -> Vec<usize> {
-        if let Some(follow) = self.follow.borrow().get(&symbol) {
-            return follow.clone();
-        }
-
-        let mut follow: HashMap<usize, HashSet<usize>> = self
-            .symbols
-            .iter()
-            .map(|symbol| (symbol.id(), HashSet::new()))
-            .collect();
-
-        loop {
-            let mut done = true;
+    /// Returns the FSTFLW set of a sequence of symbols.
+    pub fn first_follow(&self, symbols: &[usize], follow: usize) -> Vec<usize> {
+        self.first_follow_set(symbols, follow).to_vec()
+    }
 
-            for rule in &self.rules {
-                for idx in 0..rule.body.len() {
-                    let id = rule.body[idx];
+    /// Returns the set of symbols that can follow the specified symbol in any rule.
+    pub fn successors(&self, symbol: usize) -> Vec<usize> {
+        let mut result = BitSet::new();
 
-                    if self.symbols[id].is_terminal() {
-                        continue;
-                    }
+        for rule in &self.rules {
+            for (idx, &id) in rule.body.iter().enumerate() {
+                if id == symbol {
+                    result.union_with(&self.first_follow_set(rule.tail(idx + 1), rule.head));
+                }
+            }
+        }
 
-                    let mut first: HashSet<usize> = self
-                        .first_sequence(rule.tail(idx + 1))
-                        .into_iter()
-                        .collect();
+        result.to_vec()
+    }
 
-                    let from = follow[&rule.head].clone();
+    /// Returns a symbol matching the specified text. The second return value
+    /// indicates whether the symbol is a full or a partial match.
+    ///
+    /// This method returns the first full match in order of precedence if it exists,
+    /// otherwise it returns the first partial match.
+    pub fn find_symbol(&self, text: &str) -> Option<(usize, bool)> {
+        let mut symbol = None;
 
-                    if first.is_empty() {
-                        first = from;
-                    } else if first.remove(&Symbol::Null.id()) {
-                        first.extend(from);
-                    }
+        for (id, matcher) in &self.lexicon.matchers {
+            let is_full_match = match matcher.match_str(text) {
+                Match::Full => true,
+                Match::Partial => false,
+                Match::None => continue,
+            };
 
-                    let to = follow.get_mut(&id).unwrap();
-                    done &= first.difference(to).count() == 0;
-                    to.extend(first);
-                }
+            if is_full_match {
+                return Some((*id, true));
             }
 
-            if done {
-                break;
+            if symbol.is_none() {
+                symbol = Some((*id, false));
             }
         }
 
-        let follow: HashMap<usize, Vec<usize>> = follow
-            .into_iter()
-            .map(|(symbol, follow)| (symbol, util::to_sorted_vec(follow)))
-            .collect();
-
-        self.follow.replace(follow.clone());
-        follow[&symbol].clone()
+        symbol
     }
 
-    /// Returns the FIRST set of a sequence of symbols.
-    pub fn first_sequence(&self, symbols: &[usize]) -> Vec<usize> {
-        let mut result = HashSet::new();
-        let null = Symbol::Null.id();
+    /// Returns the match type of each matcher tried by `find_symbol` for the specified
+    /// text, in order of precedence.
+    pub fn trace_symbol(&self, text: &str) -> Vec<(usize, Match)> {
+        let mut matches = Vec::new();
 
-        for &symbol in symbols {
-            let per_symbol = self.first(symbol);
-            let has_null = per_symbol.contains(&null);
-            result.extend(per_symbol);
+        for (id, matcher) in &self.lexicon.matchers {
+            let current_match = matcher.match_str(text);
+            let is_full_match = current_match == Match::Full;
+            matches.push((*id, current_match));
 
-            if !has_null {
-                result.remove(&null);
+            if is_full_match {
                 break;
             }
         }
 
-        util::to_sorted_vec(result)
+        matches
     }
 
     /// Returns the FSTFLW set of a sequence of symbols.
-    pub fn first_follow(&self, symbols: &[usize], follow: usize) -> Vec<usize> {
-        let mut result = HashSet::new();
-        result.extend(self.first_sequence(symbols));
+    fn first_follow_set(&self, symbols: &[usize], follow: usize) -> BitSet {
+        let mut result = first_of(&self.first, symbols);
 
-        if result.is_empty() || result.remove(&Symbol::Null.id()) {
-            for symbol in self.follow(follow) {
-                result.extend(self.first(symbol));
+        if result.is_empty() || result.remove(Symbol::Null.id()) {
+            for symbol in self.follow[follow].iter() {
+                result.union_with(&self.first[symbol]);
             }
         }
 
-        util::to_sorted_vec(result)
+        result
     }
 
-    /// Wraps a sequence of symbols in a new rule and returns its ID.
-    pub fn wrap_symbols(&mut self, symbols: &[usize], follow: &[usize]) -> usize {
-        let head = self.symbol(symbols[0]);
-        let mut name = head.name() + "'";
+    /// Computes the FIRST and FOLLOW sets of all symbols as a fixpoint.
+    fn compute_sets(&mut self) {
+        self.first = vec![BitSet::new(); self.symbols.len()];
+        self.follow = vec![BitSet::new(); self.symbols.len()];
 
-        let mut body = vec![Symbol::End.id()];
-        body.extend_from_slice(symbols);
+        // If the symbol is a terminal, it's the only member of the FIRST set.
+        for (id, symbol) in self.symbols.iter().enumerate() {
+            if symbol.is_terminal() {
+                self.first[id].insert(id);
+            }
+        }
 
-        loop {
-            // Check if the new symbol already exists.
-            let rules = self
-                .symbols
-                .iter()
-                .filter(|symbol| symbol.is_nonterminal())
-                .find(|symbol| symbol.name() == name)
-                .map(|symbol| self.rules(symbol.id()));
+        self.fixpoint(Grammar::update_first);
+        self.fixpoint(Grammar::update_follow);
+    }
 
-            if rules.is_none() {
-                break;
-            }
+    /// Updates the FIRST and FOLLOW sets after a rule is added. The fixpoint is only
+    /// computed again if the rule changes the sets of symbols used in other rules.
+    fn add_sets(&mut self, rule: usize) {
+        self.first.resize(self.symbols.len(), BitSet::new());
+        self.follow.resize(self.symbols.len(), BitSet::new());
 
-            let rule = rules
-                .unwrap()
-                .iter()
-                .cloned()
-                .find(|rule| rule.body == body && rule.follow == follow);
+        let head = self.rules[rule].head;
+        let is_used = self.rules.iter().any(|rule| rule.body.contains(&head));
+
+        if self.update_first(rule) && is_used {
+            self.compute_sets();
+        } else if self.update_follow(rule) {
+            self.fixpoint(Grammar::update_follow);
+        }
+    }
 
-            if let Some(rule) = rule {
-                return rule.id;
+    /// Applies the update to all rules until none of the sets change.
+    fn fixpoint(&mut self, update: fn(&mut Grammar, usize) -> bool) {
+        loop {
+            let mut changed = false;
+
+            for rule in 0..self.rules.len() {
+                changed |= update(self, rule);
             }
 
-            name += "'";
+            if !changed {
+                break;
+            }
         }
+    }
 
-        // Create the wrapper symbol and rule.
-        let symbol_id = self.symbols.len();
-        let symbol = Symbol::NonTerminal(symbol_id, name);
-
-        let rule_id = self.rules.len();
-        let rule = Rule::new(rule_id, symbol_id, body, follow.to_vec());
+    /// Adds the FIRST set of the rule's body to the FIRST set of its head.
+    /// Returns `true` if the set changed.
+    fn update_first(&mut self, rule: usize) -> bool {
+        let rule = &self.rules[rule];
 
-        self.symbols.push(symbol);
-        self.rules.push(rule);
-        self.symbol_rules.insert(symbol_id, vec![rule_id]);
+        // Terminal symbols, like the start symbol, keep their FIRST set.
+        if self.symbols[rule.head].is_terminal() {
+            return false;
+        }
 
-        rule_id
+        let first = first_of(&self.first, &rule.body);
+        self.first[rule.head].union_with(&first)
     }
 
-    /// Returns a symbol matching the specified text. The second return value
-    /// indicates whether the symbol is a full or a partial match.
-    ///
-    /// This method returns the first full match if it exists,
-    /// otherwise it returns the first partial match.
-    pub fn find_symbol(&self, text: &str) -> Option<(usize, bool)> {
-        let mut symbol = None;
+    /// Adds the symbols following each nonterminal symbol in the rule's body
+    /// to its FOLLOW set. Returns `true` if any set changed.
+    fn update_follow(&mut self, rule: usize) -> bool {
+        let rule = &self.rules[rule];
+        let mut changed = false;
 
-        for (id, matcher) in &self.matchers {
-            let is_full_match = match matcher.match_str(text) {
-                Match::Full => true,
-                Match::Partial => false,
-                Match::None => continue,
-            };
-
-            if is_full_match {
-                return Some((*id, true));
+        for (idx, &id) in rule.body.iter().enumerate() {
+            if self.symbols[id].is_terminal() {
+                continue;
             }
 
-            if symbol.is_none() {
-                symbol = Some((*id, false));
+            let mut first = first_of(&self.first, rule.tail(idx + 1));
+
+            if first.is_empty() || first.remove(Symbol::Null.id()) {
+                let from = self.follow[rule.head].clone();
+                first.union_with(&from);
             }
-        }
 
-        symbol
-    }
+            changed |= self.follow[id].union_with(&first);
+        }
 
-    /// Caches the FIRST set for the specified symbol.
-    fn cache_first(&self, symbol: usize, first: &HashSet<usize>) -> Vec<usize> {
-        let first = util::to_sorted_vec(first.clone());
-        let mut cache = self.first.borrow_mut();
-        cache.insert(symbol, first.clone());
-        first
+        changed
     }
 }
 
@@ -433,3 +426,21 @@ impl Display for Error {
 }
 
 impl error::Error for Error {}
+
+/// Returns the FIRST set of a sequence of symbols from the FIRST sets of the symbols.
+fn first_of(first: &[BitSet], symbols: &[usize]) -> BitSet {
+    let mut result = BitSet::new();
+    let null = Symbol::Null.id();
+
+    for &symbol in symbols {
+        result.union_with(&first[symbol]);
+
+        // If FIRST(h) does not contain ϵ, remove it from the result.
+        if !first[symbol].contains(null) {
+            result.remove(null);
+            break;
+        }
+    }
+
+    result
+}
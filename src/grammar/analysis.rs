@@ -0,0 +1,213 @@
+use std::fmt::{self, Display, Formatter};
+
+use regex_syntax::Parser;
+use regex_syntax::hir::{Class, Hir, HirKind, Literal};
+
+use super::matcher::{Match, Matcher};
+use super::{Grammar, Policy, Symbol};
+
+/// Maximum number of example strings generated for a single expression.
+const EXAMPLE_LIMIT: usize = 32;
+
+/// Analyzes the token definitions of the grammar, and returns the list of warnings.
+///
+/// Each matcher is sampled with a set of example strings, which are matched against
+/// all other matchers. Since the examples don't cover the whole language of a regular
+/// expression, the analysis can miss some overlaps, but each warning has an example.
+pub fn analyze(grammar: &Grammar) -> Vec<Warning> {
+    let matchers = &grammar.lexicon.matchers;
+    let mut warnings = Vec::new();
+
+    let examples: Vec<Vec<String>> = matchers
+        .iter()
+        .map(|(_, matcher)| {
+            let mut examples = examples(matcher);
+            examples.retain(|example| is_full_match(matcher, example));
+            examples
+        })
+        .collect();
+
+    let mut candidates: Vec<&String> = examples.iter().flatten().collect();
+    candidates.sort();
+    candidates.dedup();
+
+    for (id, matcher) in matchers {
+        if matches_empty(matcher) {
+            warnings.push(Warning::Empty(grammar.symbol(*id).clone()));
+        }
+    }
+
+    // A token is never produced if other tokens take precedence for all examples.
+    let mut unreachable = vec![false; matchers.len()];
+
+    for (idx, examples) in examples.iter().enumerate() {
+        let winners: Vec<Option<usize>> = examples
+            .iter()
+            .map(|example| find_winner(grammar, example))
+            .collect();
+
+        if winners.is_empty() || winners.contains(&Some(idx)) {
+            continue;
+        }
+
+        if let Some((other, example)) = winners
+            .iter()
+            .zip(examples)
+            .find_map(|(winner, example)| winner.map(|other| (other, example)))
+        {
+            unreachable[idx] = true;
+            warnings.push(Warning::Unreachable(
+                grammar.symbol(matchers[idx].0).clone(),
+                grammar.symbol(matchers[other].0).clone(),
+                example.clone(),
+            ));
+        }
+    }
+
+    for (first, (first_id, first_matcher)) in matchers.iter().enumerate() {
+        for (second, (second_id, second_matcher)) in matchers.iter().enumerate().skip(first + 1) {
+            if unreachable[first] || unreachable[second] {
+                continue;
+            }
+
+            let example = candidates.iter().find(|example| {
+                is_full_match(first_matcher, example) && is_full_match(second_matcher, example)
+            });
+
+            if let Some(&example) = example {
+                warnings.push(Warning::Overlap(
+                    grammar.symbol(*first_id).clone(),
+                    grammar.symbol(*second_id).clone(),
+                    example.clone(),
+                ));
+            }
+        }
+    }
+
+    warnings
+}
+
+/// Returns the index of the matcher that produces the token for the input text.
+fn find_winner(grammar: &Grammar, text: &str) -> Option<usize> {
+    let matchers = &grammar.lexicon.matchers;
+
+    match grammar.lexicon.policy {
+        Policy::Longest => matchers
+            .iter()
+            .position(|(_, matcher)| is_full_match(matcher, text)),
+        Policy::First => {
+            let source: Vec<char> = text.chars().collect();
+
+            matchers
+                .iter()
+                .position(|(_, matcher)| matcher.longest_match(&source).is_some())
+        }
+    }
+}
+
+/// Returns `true` if the matcher fully matches the text.
+fn is_full_match(matcher: &Matcher, text: &str) -> bool {
+    matches!(matcher.match_str(text), Match::Full)
+}
+
+/// Returns `true` if the matcher can match the empty string.
+fn matches_empty(matcher: &Matcher) -> bool {
+    match matcher {
+        Matcher::Regex(regex) => regex.is_match(""),
+        Matcher::Text(string) => string.is_empty(),
+        Matcher::Group(strings) | Matcher::Folded(strings) => strings.iter().any(String::is_empty),
+    }
+}
+
+/// Returns example strings, which can be matched by the matcher.
+fn examples(matcher: &Matcher) -> Vec<String> {
+    match matcher {
+        Matcher::Regex(regex) => match Parser::new().parse(regex.as_str()) {
+            Ok(hir) => hir_examples(&hir),
+            Err(_) => Vec::new(),
+        },
+        Matcher::Text(string) => vec![string.clone()],
+        Matcher::Group(strings) | Matcher::Folded(strings) => strings.clone(),
+    }
+}
+
+/// Returns example strings for the regular expression. Each character class contributes
+/// the bounds of its ranges, and each repetition its minimum and one more repetition.
+fn hir_examples(hir: &Hir) -> Vec<String> {
+    let mut examples = match hir.kind() {
+        HirKind::Empty | HirKind::Look(_) => vec![String::new()],
+        HirKind::Literal(Literal(bytes)) => vec![String::from_utf8_lossy(bytes).into_owned()],
+        HirKind::Class(Class::Unicode(class)) => class
+            .ranges()
+            .iter()
+            .flat_map(|range| [range.start(), range.end()])
+            .map(String::from)
+            .collect(),
+        HirKind::Class(Class::Bytes(class)) => class
+            .ranges()
+            .iter()
+            .flat_map(|range| [range.start(), range.end()])
+            .map(|byte| char::from(byte).to_string())
+            .collect(),
+        HirKind::Repetition(repetition) => {
+            let examples = hir_examples(&repetition.sub);
+            let mut counts = vec![repetition.min];
+
+            if repetition.max.is_none_or(|max| max > repetition.min) {
+                counts.push(repetition.min + 1);
+            }
+
+            counts
+                .into_iter()
+                .flat_map(|count| {
+                    examples
+                        .iter()
+                        .map(move |example| example.repeat(count as usize))
+                })
+                .collect()
+        }
+        HirKind::Capture(capture) => hir_examples(&capture.sub),
+        HirKind::Concat(hirs) => hirs.iter().fold(vec![String::new()], |prefixes, hir| {
+            let examples = hir_examples(hir);
+
+            prefixes
+                .iter()
+                .flat_map(|prefix| examples.iter().map(move |example| prefix.clone() + example))
+                .take(EXAMPLE_LIMIT)
+                .collect()
+        }),
+        HirKind::Alternation(hirs) => hirs.iter().flat_map(hir_examples).collect(),
+    };
+
+    examples.dedup();
+    examples.truncate(EXAMPLE_LIMIT);
+    examples
+}
+
+/// The `Warning` enum describes a potential problem with the token definitions.
+#[derive(Debug)]
+pub enum Warning {
+    Empty(Symbol),
+    Overlap(Symbol, Symbol, String),
+    Unreachable(Symbol, Symbol, String),
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Empty(symbol) => write!(f, "Token {} matches the empty string", symbol),
+            Self::Overlap(first, second, example) => {
+                write!(
+                    f,
+                    "Tokens {} and {} overlap, e.g. {:?}",
+                    first, second, example
+                )
+            }
+            Self::Unreachable(symbol, other, example) => write!(
+                f,
+                "Token {} is never produced, because {} takes precedence, e.g. {:?}",
+                symbol, other, example
+            ),
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use std::ops::Deref;
+
+use super::Grammar;
+use super::rule::Rule;
+use super::symbol::Symbol;
+
+/// The `Derived` struct describes a grammar derived from a base grammar, which adds
+/// the wrapper symbols and rules of the LLLR parser. The base grammar isn't modified.
+#[derive(Clone, Debug)]
+pub struct Derived {
+    grammar: Grammar,
+}
+
+impl Derived {
+    /// Constructs a new derived grammar without additional rules.
+    pub fn new(base: &Grammar) -> Derived {
+        Derived {
+            grammar: base.clone(),
+        }
+    }
+
+    /// Wraps a sequence of symbols in a new rule and returns its ID.
+    pub fn wrap_symbols(&mut self, symbols: &[usize], follow: &[usize]) -> usize {
+        let grammar = &mut self.grammar;
+        let head = grammar.symbol(symbols[0]);
+        let mut name = head.name() + "'";
+
+        let mut body = vec![Symbol::End.id()];
+        body.extend_from_slice(symbols);
+
+        loop {
+            // Check if the new symbol already exists.
+            let rules = grammar
+                .symbols
+                .iter()
+                .filter(|symbol| symbol.is_nonterminal())
+                .find(|symbol| symbol.name() == name)
+                .map(|symbol| grammar.rules(symbol.id()));
+
+            if rules.is_none() {
+                break;
+            }
+
+            let rule = rules
+                .unwrap()
+                .iter()
+                .cloned()
+                .find(|rule| rule.body == body && rule.follow == follow);
+
+            if let Some(rule) = rule {
+                return rule.id;
+            }
+
+            name += "'";
+        }
+
+        // Create the wrapper symbol and rule.
+        let symbol_id = grammar.symbols.len();
+        let symbol = Symbol::NonTerminal(symbol_id, name);
+
+        let rule_id = grammar.rules.len();
+        let rule = Rule::new(rule_id, symbol_id, body, follow.to_vec());
+
+        grammar.symbols.push(symbol);
+        grammar.rules.push(rule);
+        grammar.symbol_rules.insert(symbol_id, vec![rule_id]);
+        grammar.add_sets(rule_id);
+
+        rule_id
+    }
+}
+
+impl Deref for Derived {
+    type Target = Grammar;
+
+    fn deref(&self) -> &Grammar {
+        &self.grammar
+    }
+}
@@ -0,0 +1,124 @@
+use std::collections::{HashMap, HashSet};
+
+use super::matcher::Matcher;
+
+/// The `Lexicon` struct describes how the input file is split into tokens.
+#[derive(Clone, Debug, Default)]
+pub struct Lexicon {
+    // Matchers in order of precedence.
+    pub matchers: Vec<(usize, Matcher)>,
+    pub policy: Policy,
+    pub values: HashMap<usize, ValueKind>,
+    pub tab_width: usize,
+    pub layout: Option<Layout>,
+    pub terminator: Option<Terminator>,
+}
+
+impl Lexicon {
+    /// Constructs a new lexicon.
+    pub fn new(
+        matchers: Vec<(usize, Matcher)>,
+        policy: Policy,
+        values: HashMap<usize, ValueKind>,
+        tab_width: usize,
+        layout: Option<Layout>,
+        terminator: Option<Terminator>,
+    ) -> Lexicon {
+        Lexicon {
+            matchers,
+            policy,
+            values,
+            tab_width,
+            layout,
+            terminator,
+        }
+    }
+}
+
+/// The `Policy` enum describes how the lexer chooses between tokens matching the same input.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum Policy {
+    /// Choose the longest match. Equally long matches are resolved by precedence.
+    #[default]
+    Longest,
+
+    /// Choose the first matcher in order of precedence with a match, and use its longest match.
+    First,
+}
+
+impl Policy {
+    /// Returns the policy with the specified name.
+    pub fn from_name(name: &str) -> Option<Policy> {
+        match name {
+            "longest" => Some(Self::Longest),
+            "first" => Some(Self::First),
+            _ => None,
+        }
+    }
+}
+
+/// The `ValueKind` enum describes how the lexeme of a token is decoded into a value.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ValueKind {
+    /// A decimal, hexadecimal (`0x`), octal (`0o`) or binary (`0b`) integer.
+    Integer,
+
+    /// A floating-point number.
+    Float,
+
+    /// A quoted string with escape sequences.
+    String,
+
+    /// A quoted character with escape sequences.
+    Char,
+
+    /// Either `true` or `false`.
+    Boolean,
+
+    /// An identifier, which is the lexeme itself.
+    Identifier,
+}
+
+impl ValueKind {
+    /// Returns the value kind with the specified name.
+    pub fn from_name(name: &str) -> Option<ValueKind> {
+        match name {
+            "integer" => Some(Self::Integer),
+            "float" => Some(Self::Float),
+            "string" => Some(Self::String),
+            "char" => Some(Self::Char),
+            "boolean" => Some(Self::Boolean),
+            "identifier" => Some(Self::Identifier),
+            _ => None,
+        }
+    }
+}
+
+/// The `Layout` struct describes the synthetic symbols of an indentation-sensitive grammar.
+/// A symbol is only emitted by the lexer if it's used in at least one rule.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Layout {
+    pub indent: Option<usize>,
+    pub dedent: Option<usize>,
+    pub newline: Option<usize>,
+}
+
+impl Layout {
+    /// Names of the synthetic symbols.
+    pub const INDENT: &'static str = "INDENT";
+    pub const DEDENT: &'static str = "DEDENT";
+    pub const NEWLINE: &'static str = "NEWLINE";
+
+    /// Returns `true` if the symbol is one of the synthetic symbols.
+    pub fn contains(&self, symbol: usize) -> bool {
+        [self.indent, self.dedent, self.newline].contains(&Some(symbol))
+    }
+}
+
+/// The `Terminator` struct describes a terminal symbol, which is inserted
+/// at a line break if the line ends with one of the `after` symbols.
+#[derive(Clone, Debug)]
+pub struct Terminator {
+    pub symbol: usize,
+    pub after: HashSet<usize>,
+}
@@ -1,4 +1,9 @@
+use std::fmt::{self, Display, Formatter};
+
 use regex::Regex;
+use regex_syntax::hir::{ClassUnicode, ClassUnicodeRange};
+
+use crate::generator::Literal;
 
 /// The `Matcher` enum describes a method to match an input string.
 #[derive(Clone, Debug)]
@@ -11,6 +16,9 @@ pub enum Matcher {
 
     /// Compare to a group of strings.
     Group(Vec<String>),
+
+    /// Compare to a group of case-folded strings, ignoring the case of the input.
+    Folded(Vec<String>),
 }
 
 impl Matcher {
@@ -45,24 +53,99 @@ impl Matcher {
 
                 Match::None
             }
-            Self::Group(strings) => {
-                for string in strings {
-                    if string == text {
-                        return Match::Full;
-                    }
-
-                    if string.starts_with(text) {
-                        return Match::Partial;
-                    }
-                }
+            Self::Group(strings) => match_group(strings, text),
+            Self::Folded(strings) => match_group(strings, &fold_case(text)),
+        }
+    }
 
-                Match::None
+    /// Returns the length of the longest full match at the start of the source.
+    pub fn longest_match(&self, source: &[char]) -> Option<usize> {
+        self.scan(source).0
+    }
+
+    /// Returns the length of the longest full match at the start of the source, and
+    /// `true` if the whole source is a match, so the match could continue after it.
+    pub fn scan(&self, source: &[char]) -> (Option<usize>, bool) {
+        let mut text = String::new();
+        let mut length = None;
+
+        for (idx, &ch) in source.iter().enumerate() {
+            text.push(ch);
+
+            match self.match_str(&text) {
+                Match::Full => length = Some(idx + 1),
+                Match::Partial => {}
+                Match::None => return (length, false),
+            }
+        }
+
+        (length, true)
+    }
+}
+
+impl Literal for Matcher {
+    fn literal(&self) -> String {
+        match self {
+            Self::Regex(regex) => format!("Matcher::Regex({})", regex.as_str().literal()),
+            Self::Text(string) => format!("Matcher::Text({})", string.literal()),
+            Self::Group(strings) => format!("Matcher::Group({})", strings.literal()),
+            Self::Folded(strings) => {
+                // The generated parser compares each character to the characters,
+                // which have the same case folding, so it doesn't need the folding tables.
+                let strings: Vec<Vec<String>> = (strings.iter())
+                    .map(|string| string.chars().map(unfold_case).collect())
+                    .collect();
+
+                format!("Matcher::Folded({})", strings.literal())
             }
         }
     }
 }
 
+/// Compares the input string to a group of strings.
+fn match_group(strings: &[String], text: &str) -> Match {
+    for string in strings {
+        if string == text {
+            return Match::Full;
+        }
+
+        if string.starts_with(text) {
+            return Match::Partial;
+        }
+    }
+
+    Match::None
+}
+
+/// Replaces each character with the smallest character in its simple case folding
+/// class, so strings that only differ in case are folded into the same string.
+pub fn fold_case(text: &str) -> String {
+    text.chars()
+        .map(|ch| {
+            if ch.is_ascii() {
+                return ch.to_ascii_uppercase();
+            }
+
+            let mut class = ClassUnicode::new([ClassUnicodeRange::new(ch, ch)]);
+            class.case_fold_simple();
+            class.ranges()[0].start()
+        })
+        .collect()
+}
+
+/// Returns the characters, which are replaced with the character by `fold_case`.
+fn unfold_case(ch: char) -> String {
+    let mut class = ClassUnicode::new([ClassUnicodeRange::new(ch, ch)]);
+    class.case_fold_simple();
+
+    (class.ranges().iter())
+        .flat_map(|range| range.start()..=range.end())
+        .filter(|&other| fold_case(&other.to_string()) == ch.to_string())
+        .collect()
+}
+
 /// The `Match` enum describes a match type.
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum Match {
     /// No match.
     None,
@@ -73,3 +156,13 @@ pub enum Match {
     /// Full match.
     Full,
 }
+
+impl Display for Match {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::None => write!(f, "none"),
+            Self::Partial => write!(f, "partial"),
+            Self::Full => write!(f, "full"),
+        }
+    }
+}
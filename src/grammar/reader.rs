@@ -1,18 +1,34 @@
+use std::cmp::Reverse;
 use std::collections::{HashMap, HashSet};
 use std::error;
 use std::fmt::{self, Display, Formatter};
 use std::fs;
 use std::path::Path;
+use std::sync::LazyLock;
 
 use regex::{self, Regex};
 use toml::{Value, map::Map};
 
 use crate::automaton::Action;
-use crate::grammar::{Grammar, Matcher};
+use crate::grammar::{Grammar, Layout, Lexicon, Policy, Terminator, ValueKind};
 
+use super::matcher::{self, Matcher};
 use super::rule::Rule;
 use super::symbol::Symbol;
 
+/// Fragments available in all grammars, unless they are redefined.
+const BUILTIN_FRAGMENTS: [(&str, &str); 3] = [
+    ("xid_start", r"\p{XID_Start}"),
+    ("xid_continue", r"\p{XID_Continue}"),
+    ("identifier", r"[\p{XID_Start}_]\p{XID_Continue}*"),
+];
+
+/// References to fragments in the regular expressions of tokens. Escaped characters and
+/// escapes with braces, like \p{Greek}, are skipped, and repetitions like {2,3} don't start
+/// with a letter.
+static FRAGMENT_REFERENCE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\\[pPxuU]\{[^}]*\}|\\.|\{([A-Za-z_][A-Za-z0-9_]*)\}").unwrap());
+
 /// Read the specified file, and constructs the grammar.
 pub fn read_file(filename: &Path) -> Result<Grammar, Error> {
     let contents = match fs::read_to_string(filename) {
@@ -103,6 +119,48 @@ pub fn read_file(filename: &Path) -> Result<Grammar, Error> {
         }
     }
 
+    let layout = match data.get("layout").map(Value::as_bool) {
+        Some(Some(true)) => {
+            let terminal = |name| {
+                names
+                    .get(name)
+                    .cloned()
+                    .filter(|&id: &usize| symbols[id].is_terminal())
+            };
+
+            Some(Layout {
+                indent: terminal(Layout::INDENT),
+                dedent: terminal(Layout::DEDENT),
+                newline: terminal(Layout::NEWLINE),
+            })
+        }
+        Some(Some(false)) | None => None,
+        Some(None) => return Err(Error::Key("layout".to_owned())),
+    };
+
+    // Tabs advance the column to the next multiple of the tab width.
+    let tab_width = match data.get("tab_width").map(Value::as_integer) {
+        Some(Some(width)) if width > 0 => width as usize,
+        Some(_) => return Err(Error::Key("tab_width".to_owned())),
+        None => 1,
+    };
+
+    // Tokens are matched ignoring case if they are listed, or if the value is `true`.
+    let (ignore_all, ignore_names) = match data.get("ignore_case") {
+        Some(Value::Boolean(value)) => (*value, HashSet::new()),
+        Some(Value::Array(names)) => {
+            let names: Option<HashSet<_>> = names.iter().map(Value::as_str).collect();
+
+            match names {
+                Some(names) => (false, names),
+                None => return Err(Error::Key("ignore_case".to_owned())),
+            }
+        }
+        Some(_) => return Err(Error::Key("ignore_case".to_owned())),
+        None => (false, HashSet::new()),
+    };
+
+    let ignore_case = |name: &str| ignore_all || ignore_names.contains(name);
     let mut matchers = Vec::new();
 
     // Generate regular expressions for all terminal symbols.
@@ -112,7 +170,32 @@ pub fn read_file(filename: &Path) -> Result<Grammar, Error> {
             _ => continue,
         };
 
-        matchers.push((id, Matcher::Text(name.to_owned())));
+        // Layout symbols are inserted by the lexer.
+        if layout.is_some_and(|layout| layout.contains(id)) {
+            continue;
+        }
+
+        let matcher = match ignore_case(name) {
+            true => Matcher::Folded(vec![matcher::fold_case(name)]),
+            false => Matcher::Text(name.to_owned()),
+        };
+
+        matchers.push((id, matcher));
+    }
+
+    let definitions = from_table(data, "fragments", &Value::as_table)
+        .cloned()
+        .unwrap_or_default();
+
+    let mut fragments: HashMap<_, _> = (BUILTIN_FRAGMENTS.iter())
+        .map(|&(name, pattern)| (name.to_owned(), pattern.to_owned()))
+        .collect();
+
+    for (name, pattern) in &definitions {
+        match pattern.as_str() {
+            Some(pattern) => fragments.insert(name.to_owned(), pattern.to_owned()),
+            None => return Err(Error::Fragment(name.to_owned())),
+        };
     }
 
     let definitions = from_table(data, "tokens", &Value::as_table)
@@ -131,7 +214,7 @@ pub fn read_file(filename: &Path) -> Result<Grammar, Error> {
             None => continue,
         };
 
-        let matcher = create_matcher(name, pattern)?;
+        let matcher = create_matcher(name, pattern, &fragments, ignore_case(name))?;
         matchers.push((symbol, matcher));
     }
 
@@ -139,12 +222,74 @@ pub fn read_file(filename: &Path) -> Result<Grammar, Error> {
         .cloned()
         .unwrap_or_default();
 
-    // All ignored tokens correspond to ϵ symbols.
+    // Each ignored token has its own symbol, which describes the kind of trivia.
     for (name, pattern) in &definitions {
-        let matcher = create_matcher(name, pattern)?;
-        matchers.push((Symbol::Null.id(), matcher));
+        let id = symbols.len();
+        symbols.push(Symbol::Ignored(id, name.to_owned()));
+
+        let matcher = create_matcher(name, pattern, &fragments, ignore_case(name))?;
+        matchers.push((id, matcher));
     }
 
+    let policy = match data.get("matching").map(Value::as_str) {
+        Some(Some(name)) => match Policy::from_name(name) {
+            Some(policy) => policy,
+            None => return Err(Error::Key("matching".to_owned())),
+        },
+        Some(None) => return Err(Error::Key("matching".to_owned())),
+        None => Policy::default(),
+    };
+
+    let definitions = from_table(data, "priorities", &Value::as_table)
+        .cloned()
+        .unwrap_or_default();
+
+    let mut priorities = HashMap::new();
+
+    for (name, priority) in &definitions {
+        // Priorities apply to terminal symbols and ignored tokens.
+        let symbol = symbols.iter().position(|symbol| match symbol {
+            Symbol::Terminal(_, token) | Symbol::Ignored(_, token) => token == name,
+            _ => false,
+        });
+
+        let symbol = match symbol {
+            Some(symbol) => symbol,
+            None => return Err(Error::Priority(name.to_owned())),
+        };
+
+        match priority.as_integer() {
+            Some(priority) => priorities.insert(symbol, priority),
+            None => return Err(Error::Priority(name.to_owned())),
+        };
+    }
+
+    // Matchers with a higher priority take precedence, otherwise the order is preserved.
+    matchers.sort_by_key(|(id, _)| Reverse(priorities.get(id).cloned().unwrap_or_default()));
+
+    let definitions = from_table(data, "values", &Value::as_table)
+        .cloned()
+        .unwrap_or_default();
+
+    let mut values = HashMap::new();
+
+    for (name, kind) in &definitions {
+        let symbol = match names.get(name) {
+            Some(&symbol) if symbols[symbol].is_terminal() => symbol,
+            _ => return Err(Error::Value(name.to_owned())),
+        };
+
+        match kind.as_str().and_then(ValueKind::from_name) {
+            Some(kind) => values.insert(symbol, kind),
+            None => return Err(Error::Value(name.to_owned())),
+        };
+    }
+
+    let terminator = match from_table(data, "terminator", &Value::as_table) {
+        Ok(definition) => Some(create_terminator(definition, &names, &symbols)?),
+        Err(_) => None,
+    };
+
     let mut actions = HashMap::new();
 
     let definitions = from_table(data, "actions", &Value::as_table)
@@ -175,7 +320,7 @@ pub fn read_file(filename: &Path) -> Result<Grammar, Error> {
         name,
         description,
         symbols,
-        matchers,
+        Lexicon::new(matchers, policy, values, tab_width, layout, terminator),
         start_symbol,
         rules,
         actions,
@@ -218,11 +363,22 @@ fn get_symbol(
     id
 }
 
-/// Creates a `Matcher` from a specified pattern.
-fn create_matcher(name: &str, pattern: &Value) -> Result<Matcher, Error> {
+/// Creates a `Matcher` from a specified pattern. With `ignore_case`, the matcher
+/// uses simple Unicode case folding.
+fn create_matcher(
+    name: &str,
+    pattern: &Value,
+    fragments: &HashMap<String, String>,
+    ignore_case: bool,
+) -> Result<Matcher, Error> {
     // If the pattern is a single string, create a regex matcher.
     if let Some(pattern) = pattern.as_str() {
-        let pattern = format!("^{}$", pattern);
+        let pattern = expand(pattern, fragments, &mut Vec::new())?;
+
+        let pattern = match ignore_case {
+            true => format!("^(?i:{})$", pattern),
+            false => format!("^{}$", pattern),
+        };
 
         return match Regex::new(&pattern) {
             Ok(regex) => Ok(Matcher::Regex(regex)),
@@ -247,28 +403,115 @@ fn create_matcher(name: &str, pattern: &Value) -> Result<Matcher, Error> {
         group.push(pattern);
     }
 
+    if ignore_case {
+        let group = group
+            .iter()
+            .map(|string| matcher::fold_case(string))
+            .collect();
+        return Ok(Matcher::Folded(group));
+    }
+
     Ok(Matcher::Group(group))
 }
 
+/// Replaces the references to fragments in the pattern with their expanded patterns.
+/// The `path` contains the fragments currently being expanded, which is used to detect cycles.
+fn expand(
+    pattern: &str,
+    fragments: &HashMap<String, String>,
+    path: &mut Vec<String>,
+) -> Result<String, Error> {
+    let mut result = String::new();
+    let mut last = 0;
+
+    for captures in FRAGMENT_REFERENCE.captures_iter(pattern) {
+        let name = match captures.get(1) {
+            Some(name) => name.as_str(),
+            None => continue,
+        };
+
+        let fragment = match fragments.get(name) {
+            Some(fragment) => fragment,
+            None => return Err(Error::Undefined(name.to_owned())),
+        };
+
+        if path.iter().any(|other| other == name) {
+            path.push(name.to_owned());
+            return Err(Error::Cycle(path.clone()));
+        }
+
+        path.push(name.to_owned());
+        let fragment = expand(fragment, fragments, path)?;
+        path.pop();
+
+        let range = captures.get(0).unwrap().range();
+        result.push_str(&pattern[last..range.start]);
+        result.push_str(&format!("(?:{})", fragment));
+        last = range.end;
+    }
+
+    result.push_str(&pattern[last..]);
+    Ok(result)
+}
+
+/// Creates a `Terminator` from the specified definition.
+fn create_terminator(
+    definition: &Map<String, Value>,
+    names: &HashMap<String, usize>,
+    symbols: &[Symbol],
+) -> Result<Terminator, Error> {
+    let terminal = |name: &str| {
+        names
+            .get(name)
+            .cloned()
+            .filter(|&id| symbols[id].is_terminal())
+            .ok_or_else(|| Error::Terminator(name.to_owned()))
+    };
+
+    let symbol = terminal(from_table(definition, "symbol", &Value::as_str)?)?;
+    let mut after = HashSet::new();
+
+    for name in from_table(definition, "after", &Value::as_array)? {
+        match name.as_str() {
+            Some(name) => after.insert(terminal(name)?),
+            None => return Err(Error::Key("after".to_owned())),
+        };
+    }
+
+    Ok(Terminator { symbol, after })
+}
+
 #[derive(Debug)]
 pub enum Error {
     Action(String),
+    Cycle(Vec<String>),
     File(String),
+    Fragment(String),
     Key(String),
+    Priority(String),
     Regex(String),
     Rule(String),
+    Terminator(String),
     Token(String),
+    Undefined(String),
+    Value(String),
 }
 
 impl Display for Error {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         match self {
             Self::Action(name) => write!(f, "Cannot parse action for {}", name),
+            Self::Cycle(path) => write!(f, "Cannot expand fragment cycle {}", path.join(" → ")),
             Self::File(error) => write!(f, "Cannot read file {}", error),
+            Self::Fragment(name) => write!(f, "Cannot parse fragment '{}'", name),
             Self::Key(name) => write!(f, "Cannot parse key '{}'", name),
+            Self::Priority(name) => write!(f, "Cannot parse priority for {}", name),
             Self::Regex(pattern) => write!(f, "Cannot parse expression /{}/", pattern),
             Self::Rule(name) => write!(f, "Cannot parse rule {}", name),
+            Self::Terminator(name) => write!(f, "Cannot parse terminator {}", name),
             Self::Token(name) => write!(f, "Cannot parse token '{}'", name),
+            Self::Undefined(name) => write!(f, "Cannot find fragment '{}'", name),
+            Self::Value(name) => write!(f, "Cannot parse value kind for {}", name),
         }
     }
 }
@@ -2,8 +2,10 @@ use std::cmp::Ordering;
 use std::collections::HashSet;
 use std::hash::{Hash, Hasher};
 
+use crate::generator::Literal;
 use crate::grammar::{Grammar, Position, Symbol};
 use crate::util::AsString;
+use crate::util::encoding::{Decoder, Encode, Encoder, Error};
 
 /// The `Rule` struct describes a grammar rule.
 ///
@@ -92,3 +94,31 @@ impl AsString for Rule {
         format!("({}) {} → {}", self.id, head, body)
     }
 }
+
+impl Encode for Rule {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.write(&self.id);
+        encoder.write(&self.head);
+        encoder.write(&self.body);
+        encoder.write(&self.follow);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Result<Rule, Error> {
+        Ok(Rule::new(
+            decoder.read()?,
+            decoder.read()?,
+            decoder.read()?,
+            decoder.read()?,
+        ))
+    }
+}
+
+impl Literal for Rule {
+    fn literal(&self) -> String {
+        format!(
+            "Rule {{ head: {}, body: {} }}",
+            self.head,
+            self.body.literal()
+        )
+    }
+}
@@ -1,5 +1,8 @@
 use std::fmt::{self, Display, Formatter};
 
+use crate::generator::Literal;
+use crate::util::encoding::{Decoder, Encode, Encoder, Error};
+
 /// The `Symbol` enum describes an element of a grammar rule.
 #[derive(Clone, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub enum Symbol {
@@ -12,17 +15,23 @@ pub enum Symbol {
     /// An empty symbol.
     Null,
 
+    /// An unrecognized sequence of characters in the input file.
+    Error,
+
     /// A nonterminal symbol, which is replaced with a set of terminal symbols.
     NonTerminal(usize, String),
 
     /// A terminal symbol, indicating a token in the input file.
     Terminal(usize, String),
+
+    /// An ignored symbol, indicating whitespace or comments in the input file.
+    Ignored(usize, String),
 }
 
 impl Symbol {
     /// Returns a list of internal symbols.
     pub fn internal() -> Vec<Symbol> {
-        vec![Self::Start, Self::End, Self::Null]
+        vec![Self::Start, Self::End, Self::Null, Self::Error]
     }
 
     /// Returns the ID of the symbol.
@@ -31,7 +40,8 @@ impl Symbol {
             Self::Start => 0,
             Self::End => 1,
             Self::Null => 2,
-            Self::NonTerminal(id, _) | Self::Terminal(id, _) => *id,
+            Self::Error => 3,
+            Self::NonTerminal(id, _) | Self::Terminal(id, _) | Self::Ignored(id, _) => *id,
         }
     }
 
@@ -41,7 +51,8 @@ impl Symbol {
             Self::Start => "^".to_owned(),
             Self::End => "$".to_owned(),
             Self::Null => "ϵ".to_owned(),
-            Self::NonTerminal(_, name) => name.clone(),
+            Self::Error => "⊥".to_owned(),
+            Self::NonTerminal(_, name) | Self::Ignored(_, name) => name.clone(),
             Self::Terminal(_, name) => {
                 if name.contains('\'') {
                     format!("\"{}\"", name)
@@ -62,9 +73,17 @@ impl Symbol {
         matches!(self, Self::NonTerminal(..))
     }
 
+    /// Returns `true` if the symbol is ignored.
+    pub fn is_ignored(&self) -> bool {
+        matches!(self, Self::Ignored(..))
+    }
+
     /// Returns `true` if the symbol is internal.
     pub fn is_internal(&self) -> bool {
-        !matches!(self, Self::NonTerminal(..) | Self::Terminal(..))
+        !matches!(
+            self,
+            Self::NonTerminal(..) | Self::Terminal(..) | Self::Ignored(..)
+        )
     }
 }
 
@@ -73,3 +92,56 @@ impl Display for Symbol {
         write!(f, "{}", self.name())
     }
 }
+
+impl Encode for Symbol {
+    fn encode(&self, encoder: &mut Encoder) {
+        let kind: usize = match self {
+            Self::Start => 0,
+            Self::End => 1,
+            Self::Null => 2,
+            Self::Error => 3,
+            Self::NonTerminal(..) => 4,
+            Self::Terminal(..) => 5,
+            Self::Ignored(..) => 6,
+        };
+
+        encoder.write(&kind);
+
+        if let Self::NonTerminal(id, name) | Self::Terminal(id, name) | Self::Ignored(id, name) =
+            self
+        {
+            encoder.write(id);
+            encoder.write(name);
+        }
+    }
+
+    fn decode(decoder: &mut Decoder) -> Result<Symbol, Error> {
+        match decoder.read::<usize>()? {
+            0 => Ok(Self::Start),
+            1 => Ok(Self::End),
+            2 => Ok(Self::Null),
+            3 => Ok(Self::Error),
+            4 => Ok(Self::NonTerminal(decoder.read()?, decoder.read()?)),
+            5 => Ok(Self::Terminal(decoder.read()?, decoder.read()?)),
+            6 => Ok(Self::Ignored(decoder.read()?, decoder.read()?)),
+            _ => Err(Error::Invalid),
+        }
+    }
+}
+
+impl Literal for Symbol {
+    fn literal(&self) -> String {
+        let kind = match self {
+            Self::NonTerminal(..) => "NonTerminal",
+            Self::Terminal(..) => "Terminal",
+            Self::Ignored(..) => "Ignored",
+            _ => "Internal",
+        };
+
+        format!(
+            "Symbol {{ name: {}, kind: Kind::{} }}",
+            self.to_string().literal(),
+            kind
+        )
+    }
+}
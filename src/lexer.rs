@@ -1,108 +1,624 @@
+use std::collections::VecDeque;
 use std::error;
 use std::fmt::{self, Display, Formatter};
-use std::fs;
+use std::fs::File;
+use std::io::{ErrorKind, Read};
+use std::mem;
 use std::path::Path;
+use std::str;
+use std::sync::Arc;
 
-use crate::grammar::{Grammar, Symbol};
+use memmap2::Mmap;
 
+use crate::grammar::{Grammar, Match, Policy, Symbol};
+
+mod layout;
+mod lexeme;
+mod source_map;
 mod span;
+mod terminator;
 mod token;
+mod token_source;
+mod trace;
+mod trivia;
+mod value;
 
+pub use lexeme::Lexeme;
+pub use source_map::{FileId, SourceFile, SourceMap};
 pub use span::Span;
+
+use span::advance;
 pub use token::Token;
+pub use token_source::{NamedTokens, TokenSource};
+pub use trace::{Step, Trace};
+pub use value::Value;
+
+/// Size of the chunks read from the input, in bytes.
+const CHUNK_SIZE: usize = 64 * 1024;
 
 /// Returns the list of tokens in the input file using lexical analysis.
 pub fn get_tokens(filename: &Path, grammar: &Grammar) -> Result<Vec<Token>, Error> {
-    let source: Vec<char> = match fs::read_to_string(filename) {
-        Ok(contents) => contents.chars().collect(),
+    let file = map_file(filename)?;
+    Lexer::new(&file[..], FileId::default(), grammar, false)
+        .filter(|result| !matches!(result, Err(error) if error.is_value()))
+        .collect()
+}
+
+/// Returns the list of tokens in the input file using lexical analysis, and the list
+/// of diagnostics. Each unrecognized sequence of characters is returned as an error
+/// token, and the analysis continues with the next character that starts a valid match.
+pub fn recover_tokens(
+    filename: &Path,
+    grammar: &Grammar,
+) -> Result<(Vec<Token>, Vec<Error>), Error> {
+    let file = map_file(filename)?;
+    Lexer::new(&file[..], FileId::default(), grammar, true).tokenize()
+}
+
+/// Maps the input file into memory, so large files are read on demand.
+pub fn map_file(filename: &Path) -> Result<Mmap, Error> {
+    let file = match File::open(filename) {
+        Ok(file) => file,
         Err(error) => return Err(Error::File(error.to_string())),
     };
 
-    let mut idx = 0;
-    let mut position = (1, 1);
+    // SAFETY: The mapped file must not be modified by other processes while it's read.
+    match unsafe { Mmap::map(&file) } {
+        Ok(mmap) => Ok(mmap),
+        Err(error) => Err(Error::File(error.to_string())),
+    }
+}
 
-    let mut current_match;
-    let mut text = String::new();
-    let mut span = Span::new(position);
+/// The `Lexer` struct splits the input into tokens using lexical analysis.
+///
+/// The input is read in chunks, and the tokens are produced lazily by the iterator.
+/// With error recovery, the diagnostics are produced as errors between the tokens.
+/// Otherwise, the lexer stops after the first error.
+pub struct Lexer<'a, R> {
+    grammar: &'a Grammar,
+    reader: R,
+    recover: bool,
+    // Characters read from the input, and the index of the first unprocessed character.
+    buffer: Vec<char>,
+    idx: usize,
+    // Text of the buffer shared by the lexemes, and the byte index of the first
+    // unprocessed character.
+    text: Arc<str>,
+    start: usize,
+    // Incomplete UTF-8 sequence at the end of the last chunk.
+    bytes: Vec<u8>,
+    complete: bool,
+    file: FileId,
+    position: (usize, usize),
+    offset: usize,
+    terminator: Option<terminator::State<'a>>,
+    layout: Option<layout::State<'a>>,
+    trivia: trivia::State<'a>,
+    output: VecDeque<Result<Token, Error>>,
+    finished: bool,
+    trace: Option<Vec<Trace>>,
+}
 
-    let mut last_match: Option<(Token, usize)> = None;
-    let mut tokens = Vec::new();
+impl<'a, 'b> Lexer<'a, &'b [u8]> {
+    /// Constructs a new lexer, which reads the input from the text.
+    pub fn from_text(
+        text: &'b str,
+        file: FileId,
+        grammar: &'a Grammar,
+        recover: bool,
+    ) -> Lexer<'a, &'b [u8]> {
+        Lexer::new(text.as_bytes(), file, grammar, recover)
+    }
+}
 
-    while idx < source.len() {
-        let ch = source[idx];
+impl<'a, R: Read> Lexer<'a, R> {
+    /// Constructs a new lexer, which reads the input from the reader.
+    pub fn new(reader: R, file: FileId, grammar: &'a Grammar, recover: bool) -> Lexer<'a, R> {
+        let lexicon = &grammar.lexicon;
 
-        text.push(ch);
-        current_match = grammar.find_symbol(&text);
+        Lexer {
+            grammar,
+            reader,
+            recover,
+            buffer: Vec::new(),
+            idx: 0,
+            text: Arc::from(""),
+            start: 0,
+            bytes: Vec::new(),
+            complete: false,
+            file,
+            position: (1, 1),
+            offset: 0,
+            terminator: (lexicon.terminator.as_ref())
+                .map(|terminator| terminator::State::new(grammar, terminator)),
+            layout: (lexicon.layout.as_ref()).map(|layout| layout::State::new(grammar, layout)),
+            trivia: trivia::State::new(grammar),
+            output: VecDeque::new(),
+            finished: false,
+            trace: None,
+        }
+    }
+
+    /// Consumes the lexer, and returns the list of tokens and the list of diagnostics.
+    /// An error reading the input is returned immediately.
+    pub fn tokenize(self) -> Result<(Vec<Token>, Vec<Error>), Error> {
+        let mut tokens = Vec::new();
+        let mut diagnostics = Vec::new();
+
+        for result in self {
+            match result {
+                Ok(token) => tokens.push(token),
+                Err(error @ Error::File(_)) => return Err(error),
+                Err(error) => diagnostics.push(error),
+            }
+        }
+
+        Ok((tokens, diagnostics))
+    }
+
+    /// Starts recording the decisions of the lexer for each token.
+    pub fn enable_trace(&mut self) {
+        self.trace.get_or_insert_with(Vec::new);
+    }
+
+    /// Returns the decisions recorded since the last call.
+    pub fn take_trace(&mut self) -> Vec<Trace> {
+        self.trace.as_mut().map(mem::take).unwrap_or_default()
+    }
+
+    /// Finds the next token in the input, and passes it through the lexer stages.
+    fn step(&mut self) {
+        if self.idx == self.buffer.len() {
+            if self.complete {
+                self.finish();
+            } else if let Err(error) = self.fill() {
+                self.fail(error);
+            }
+
+            return;
+        }
+
+        let mut steps = Vec::new();
+
+        let (symbol, length, span) = loop {
+            let source = &self.buffer[self.idx..];
+            steps.clear();
+            let trace = self.trace.is_some().then_some(&mut steps);
+
+            match find_token(source, self.position, self.grammar, self.complete, trace) {
+                Some(Ok(found)) => break found,
+                Some(Err(Error::Token(lexeme, mut span))) if !self.recover => {
+                    self.record(mem::take(&mut steps));
+                    self.locate(&mut span, &lexeme);
+                    self.fail(Error::Token(lexeme, span));
+                    return;
+                }
+                Some(Err(error)) if !self.recover => {
+                    self.fail(error);
+                    return;
+                }
+                Some(Err(_)) => {
+                    if let Some(found) =
+                        find_error(source, self.position, self.grammar, self.complete)
+                    {
+                        break found;
+                    }
+                }
+                None => {}
+            }
+
+            // The token can continue in the next chunk.
+            if let Err(error) = self.fill() {
+                self.fail(error);
+                return;
+            }
+        };
+
+        self.record(steps);
+        let mut token = self.take(symbol, length, span);
+
+        if token.symbol == Symbol::Error.id() {
+            let error = Error::Token(token.lexeme.to_string(), token.span);
+            self.output.push_back(Err(error));
+        }
+
+        // A lexeme that cannot be decoded is still a valid token, which has no value.
+        if let Some(&kind) = self.grammar.lexicon.values.get(&token.symbol) {
+            match value::decode(kind, &token.lexeme) {
+                Ok(value) => token.value = Some(value),
+                Err(reason) => {
+                    let error = Error::Value(token.lexeme.to_string(), reason, token.span);
+                    self.output.push_back(Err(error));
+                }
+            }
+        }
+
+        let end = span_after(&token, self.grammar.lexicon.tab_width);
+        self.position = end.start;
+        self.offset = end.offset;
+
+        let mut tokens = Vec::new();
+
+        match &mut self.terminator {
+            Some(terminator) => terminator.push(token, &mut tokens),
+            None => tokens.push(token),
+        }
+
+        self.stage(tokens);
+    }
+
+    /// Records the decisions for the token at the current offset.
+    fn record(&mut self, steps: Vec<Step>) {
+        if let Some(trace) = &mut self.trace {
+            let offset = self.offset;
+            trace.push(Trace { offset, steps });
+        }
+    }
+
+    /// Constructs the token from the next `length` characters, and seeks to its end.
+    /// The lexeme borrows its text from the buffer instead of copying it.
+    fn take(&mut self, symbol: usize, length: usize, mut span: Span) -> Token {
+        let size: usize = self.buffer[self.idx..self.idx + length]
+            .iter()
+            .map(|ch| ch.len_utf8())
+            .sum();
+        let lexeme = Lexeme::new(self.text.clone(), self.start..self.start + size);
+
+        self.locate(&mut span, &lexeme);
+        self.idx += length;
+        self.start += size;
+
+        Token::new(symbol, lexeme, span)
+    }
+
+    /// Sets the file and the byte range of the lexeme's span.
+    fn locate(&self, span: &mut Span, lexeme: &str) {
+        span.file = self.file;
+        span.offset = self.offset;
+        span.len = lexeme.len();
+    }
+
+    /// Passes the tokens through the layout and trivia stages.
+    fn stage(&mut self, tokens: Vec<Token>) {
+        let mut diagnostics = Vec::new();
+
+        let tokens = match &mut self.layout {
+            Some(layout) => {
+                let mut result = Vec::new();
+
+                for token in tokens {
+                    layout.push(token, &mut result, &mut diagnostics);
+                }
+
+                result
+            }
+            None => tokens,
+        };
+
+        let mut result = Vec::new();
+
+        for token in tokens {
+            self.trivia.push(token, &mut result);
+        }
+
+        self.output.extend(diagnostics.into_iter().map(Err));
+        self.output.extend(result.into_iter().map(Ok));
+    }
+
+    /// Closes all lexer stages at the end of the input.
+    fn finish(&mut self) {
+        let mut tokens = Vec::new();
+
+        if let Some(terminator) = &mut self.terminator {
+            terminator.finish(&mut tokens);
+        }
+
+        self.stage(tokens);
+
+        let mut tokens = Vec::new();
+        let end = Span::point(self.file, self.position, self.offset);
+
+        if let Some(layout) = &mut self.layout {
+            layout.finish(end, &mut tokens);
+        }
+
+        let mut result = Vec::new();
 
-        // There should always be at least a partial match.
-        if current_match.is_none() && last_match.is_none() {
-            return Err(Error::Token(text, span));
+        for token in tokens {
+            self.trivia.push(token, &mut result);
         }
 
-        // If there's no match for the current string, take the last match.
-        if current_match.is_none() {
-            let (token, end_idx) = last_match.unwrap();
+        self.trivia.finish(end, &mut result);
+        self.output.extend(result.into_iter().map(Ok));
+        self.finished = true;
+    }
+
+    /// Stops the lexer with an error.
+    fn fail(&mut self, error: Error) {
+        self.output.push_back(Err(error));
+        self.finished = true;
+    }
+
+    /// Reads the next chunk of the input.
+    fn fill(&mut self) -> Result<(), Error> {
+        // Discard the processed characters.
+        self.buffer.drain(..self.idx);
+        self.idx = 0;
+
+        let mut chunk = vec![0; CHUNK_SIZE];
+
+        let count = loop {
+            match self.reader.read(&mut chunk) {
+                Ok(count) => break count,
+                Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+                Err(error) => return Err(Error::File(error.to_string())),
+            }
+        };
+
+        if count == 0 {
+            self.complete = true;
+
+            if !self.bytes.is_empty() {
+                return Err(Error::File("incomplete utf-8 byte sequence".to_owned()));
+            }
+
+            return Ok(());
+        }
+
+        self.bytes.extend_from_slice(&chunk[..count]);
+
+        // A chunk can end in the middle of a character.
+        let length = match str::from_utf8(&self.bytes) {
+            Ok(text) => text.len(),
+            Err(error) if error.error_len().is_none() => error.valid_up_to(),
+            Err(error) => return Err(Error::File(error.to_string())),
+        };
+
+        let text = str::from_utf8(&self.bytes[..length]).unwrap();
+        self.buffer.extend(text.chars());
+
+        // Tokens from the previous chunks keep their own copy of the text.
+        let mut window = String::with_capacity(self.text.len() - self.start + length);
+        window.push_str(&self.text[self.start..]);
+        window.push_str(text);
+        self.text = Arc::from(window);
+        self.start = 0;
+
+        self.bytes.drain(..length);
+
+        Ok(())
+    }
+}
+
+impl<R: Read> Iterator for Lexer<'_, R> {
+    type Item = Result<Token, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(result) = self.output.pop_front() {
+                // Without error recovery, the lexer stops after the first error. A value
+                // that cannot be decoded doesn't stop the lexer, because its token is valid.
+                if matches!(result, Err(ref error) if !error.is_value()) && !self.recover {
+                    self.output.clear();
+                    self.finished = true;
+                }
+
+                return Some(result);
+            }
 
-            // Ignore ϵ symbols.
-            if token.symbol != Symbol::Null.id() {
-                tokens.push(token.clone());
+            if self.finished {
+                return None;
             }
 
-            // Seek back to the end of the last match.
-            let ch = token.last().unwrap();
-            position = advance(token.span.end, ch);
-            idx = end_idx + 1;
+            self.step();
+        }
+    }
+}
+
+/// Reconstructs the source text from the list of tokens.
+pub fn unparse(tokens: &[Token]) -> String {
+    let mut text = String::new();
+
+    for token in tokens {
+        token.unparse(&mut text);
+    }
+
+    text
+}
+
+/// Returns the symbol, the length in characters and the span of the token at the start
+/// of the source, according to the matching policy. If the source is not `complete`,
+/// and the token could continue after the end of the source, `None` is returned.
+/// The decisions of the matchers are recorded in `trace`, if provided.
+fn find_token(
+    source: &[char],
+    position: (usize, usize),
+    grammar: &Grammar,
+    complete: bool,
+    trace: Option<&mut Vec<Step>>,
+) -> Option<Result<(usize, usize, Span), Error>> {
+    match grammar.lexicon.policy {
+        Policy::Longest => find_longest(source, position, grammar, complete, trace),
+        Policy::First => find_first(source, position, grammar, complete, trace),
+    }
+}
+
+/// Returns the longest token at the start of the source.
+fn find_longest(
+    source: &[char],
+    position: (usize, usize),
+    grammar: &Grammar,
+    complete: bool,
+    mut trace: Option<&mut Vec<Step>>,
+) -> Option<Result<(usize, usize, Span), Error>> {
+    let mut text = String::new();
+    let mut span = Span::new(position);
+    let mut last_match = None;
+    let mut is_open = true;
 
-            text = String::new();
-            span = Span::new(position);
-            last_match = None;
+    for (idx, &ch) in source.iter().enumerate() {
+        text.push(ch);
 
-            continue;
+        if let Some(trace) = trace.as_mut() {
+            let matches = grammar.trace_symbol(&text);
+            trace.push(Step::new(text.clone(), matches));
         }
 
+        // Stop at the first character without at least a partial match.
+        let (id, is_full_match) = match grammar.find_symbol(&text) {
+            Some(current_match) => current_match,
+            None => {
+                is_open = false;
+                break;
+            }
+        };
+
         // Save the current full match.
-        if let Some((id, true)) = current_match {
-            let token = Token::new(id, text.clone(), span);
-            last_match = Some((token, idx));
+        if is_full_match {
+            last_match = Some((id, idx + 1, span));
+        }
+
+        span.end = advance(span.end, ch, grammar.lexicon.tab_width);
+    }
+
+    if is_open && !complete {
+        return None;
+    }
+
+    match last_match {
+        Some(token) => Some(Ok(token)),
+        None => Some(Err(Error::Token(text, span))),
+    }
+}
+
+/// Returns the token of the first matcher with a match at the start of the source.
+fn find_first(
+    source: &[char],
+    position: (usize, usize),
+    grammar: &Grammar,
+    complete: bool,
+    trace: Option<&mut Vec<Step>>,
+) -> Option<Result<(usize, usize, Span), Error>> {
+    let mut matches = Vec::new();
+
+    for (id, matcher) in &grammar.lexicon.matchers {
+        let (length, is_open) = matcher.scan(source);
+
+        if is_open && !complete {
+            return None;
+        }
+
+        let length = match length {
+            Some(length) => length,
+            None => {
+                matches.push((*id, Match::None));
+                continue;
+            }
+        };
+
+        if let Some(trace) = trace {
+            matches.push((*id, Match::Full));
+            trace.push(Step::new(source[..length].iter().collect(), matches));
         }
 
-        position = advance(position, ch);
-        span.end = position;
-        idx += 1;
+        let mut span = Span::new(position);
+
+        for &ch in &source[..length - 1] {
+            span.end = advance(span.end, ch, grammar.lexicon.tab_width);
+        }
+
+        return Some(Ok((*id, length, span)));
     }
 
-    if last_match.is_none() {
-        return Err(Error::Token(text, span));
+    if let Some(trace) = trace {
+        trace.push(Step::new(String::new(), matches));
     }
 
-    Ok(tokens)
+    Some(Err(Error::Token(
+        source[0].to_string(),
+        Span::new(position),
+    )))
 }
 
-/// Advances the position in the file based on the current character.
-fn advance(position: (usize, usize), ch: char) -> (usize, usize) {
-    let (row, column) = position;
+/// Returns an error token for the unrecognized characters at the start of the source.
+/// The token ends before the next character that starts a valid match.
+fn find_error(
+    source: &[char],
+    position: (usize, usize),
+    grammar: &Grammar,
+    complete: bool,
+) -> Option<(usize, usize, Span)> {
+    let tab_width = grammar.lexicon.tab_width;
+    let mut text = String::new();
+    let mut span = Span::new(position);
+
+    for (idx, &ch) in source.iter().enumerate() {
+        if idx > 0 {
+            let next = advance(span.end, text.chars().last().unwrap(), tab_width);
 
-    if ch == '\n' {
-        return (row + 1, 1);
+            match find_token(&source[idx..], next, grammar, complete, None) {
+                Some(Ok(_)) => break,
+                Some(Err(_)) => span.end = next,
+                None => return None,
+            }
+        }
+
+        text.push(ch);
     }
 
-    (row, column + 1)
+    // The unrecognized characters can continue in the next chunk.
+    if text.chars().count() == source.len() && !complete {
+        return None;
+    }
+
+    Some((Symbol::Error.id(), text.chars().count(), span))
+}
+
+/// Returns an empty span at the position following the token.
+fn span_after(token: &Token, tab_width: usize) -> Span {
+    let position = advance(token.span.end, token.last().unwrap(), tab_width);
+    Span::point(
+        token.span.file,
+        position,
+        token.span.offset + token.span.len,
+    )
 }
 
 #[derive(Debug)]
 pub enum Error {
     File(String),
+    Indent(Span),
+    Kind(String, Span),
     Token(String, Span),
+    Value(String, String, Span),
+}
+
+impl Error {
+    /// Returns whether the error is a value that cannot be decoded, which is a warning
+    /// about a valid token.
+    pub fn is_value(&self) -> bool {
+        matches!(self, Self::Value(..))
+    }
 }
 
 impl Display for Error {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
         match self {
             Self::File(error) => write!(f, "Cannot read file {}", error),
+            Self::Indent(span) => write!(f, "Inconsistent indentation @ {}", span),
+            Self::Kind(kind, span) => write!(f, "Unknown token kind '{}' @ {}", kind, span),
             Self::Token(lexeme, span) => {
                 let lexeme = lexeme.escape_default();
                 write!(f, "Cannot recognize token '{}' @ {}", lexeme, span)
             }
+            Self::Value(lexeme, reason, span) => {
+                let lexeme = lexeme.escape_default();
+                write!(
+                    f,
+                    "Cannot decode value '{}' ({}) @ {}",
+                    lexeme, reason, span
+                )
+            }
         }
     }
 }
@@ -0,0 +1,105 @@
+use crate::grammar::{Grammar, Layout};
+
+use super::{Error, Span, Token, span_after};
+
+/// The `State` struct inserts the INDENT, DEDENT and NEWLINE tokens.
+///
+/// A logical line starts with the first token after a line break in ignored tokens,
+/// so blank lines and comments don't affect indentation. Line breaks inside
+/// brackets continue the current logical line.
+///
+/// Inconsistent indentation is reported as a diagnostic,
+/// and the column is used as the indentation of a new block.
+pub struct State<'a> {
+    layout: &'a Layout,
+    grammar: &'a Grammar,
+    // Indentation columns of the enclosing blocks.
+    levels: Vec<usize>,
+    depth: usize,
+    line_break: bool,
+    last_span: Option<Span>,
+}
+
+impl<'a> State<'a> {
+    /// Constructs a new state for the layout.
+    pub fn new(grammar: &'a Grammar, layout: &'a Layout) -> State<'a> {
+        State {
+            layout,
+            grammar,
+            levels: vec![1],
+            depth: 0,
+            line_break: true,
+            last_span: None,
+        }
+    }
+
+    /// Appends the token to the output, preceded by the layout tokens if it starts a logical line.
+    pub fn push(&mut self, token: Token, output: &mut Vec<Token>, diagnostics: &mut Vec<Error>) {
+        if self.grammar.symbol(token.symbol).is_ignored() {
+            self.line_break |= token.lexeme.contains('\n');
+            output.push(token);
+            return;
+        }
+
+        // Inserted terminators don't start a logical line.
+        if token.synthetic {
+            output.push(token);
+            return;
+        }
+
+        if self.line_break && self.depth == 0 {
+            let span = Span::point(token.span.file, token.span.start, token.span.offset);
+            let column = span.start.1;
+
+            if let Some(last_span) = self.last_span {
+                push(output, self.layout.newline, last_span);
+            }
+
+            if column > *self.levels.last().unwrap() {
+                self.levels.push(column);
+                push(output, self.layout.indent, span);
+            }
+
+            while column < *self.levels.last().unwrap() {
+                self.levels.pop();
+                push(output, self.layout.dedent, span);
+            }
+
+            // The column should match an enclosing block.
+            if column != *self.levels.last().unwrap() {
+                diagnostics.push(Error::Indent(span));
+                self.levels.push(column);
+            }
+        }
+
+        match token.lexeme.as_str() {
+            "(" | "[" | "{" => self.depth += 1,
+            ")" | "]" | "}" => self.depth = self.depth.saturating_sub(1),
+            _ => {}
+        }
+
+        self.line_break = false;
+        self.last_span = Some(span_after(&token, self.grammar.lexicon.tab_width));
+        output.push(token);
+    }
+
+    /// Closes the last logical line and all open blocks at the end of the file.
+    pub fn finish(&mut self, end: Span, output: &mut Vec<Token>) {
+        if let Some(last_span) = self.last_span {
+            push(output, self.layout.newline, last_span);
+        }
+
+        for _ in 1..self.levels.len() {
+            push(output, self.layout.dedent, end);
+        }
+
+        self.levels.truncate(1);
+    }
+}
+
+/// Pushes a synthetic token if the grammar uses its symbol.
+fn push(tokens: &mut Vec<Token>, symbol: Option<usize>, span: Span) {
+    if let Some(symbol) = symbol {
+        tokens.push(Token::synthetic(symbol, span));
+    }
+}
@@ -0,0 +1,100 @@
+use std::fmt::{self, Debug, Display, Formatter};
+use std::ops::{Deref, Range};
+use std::sync::Arc;
+
+/// The `Lexeme` struct is the text of a token. It borrows a range of the source text,
+/// which is shared by all tokens in the same chunk, so cloning a lexeme doesn't copy it.
+#[derive(Clone, Default)]
+pub struct Lexeme {
+    text: Option<Arc<str>>,
+    start: usize,
+    end: usize,
+}
+
+impl Lexeme {
+    /// Constructs a new lexeme from the byte range of the source text.
+    pub fn new(text: Arc<str>, range: Range<usize>) -> Lexeme {
+        Lexeme {
+            text: Some(text),
+            start: range.start,
+            end: range.end,
+        }
+    }
+
+    /// Splits the lexeme at the byte index, which needs to be at a character boundary.
+    /// Both parts share the source text with the lexeme.
+    pub fn split_at(&self, mid: usize) -> (Lexeme, Lexeme) {
+        let first = Lexeme {
+            end: self.start + mid,
+            ..self.clone()
+        };
+
+        let second = Lexeme {
+            start: self.start + mid,
+            ..self.clone()
+        };
+
+        (first, second)
+    }
+
+    /// Returns the lexeme as a string slice.
+    pub fn as_str(&self) -> &str {
+        match &self.text {
+            Some(text) => &text[self.start..self.end],
+            None => "",
+        }
+    }
+}
+
+impl Deref for Lexeme {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl From<&str> for Lexeme {
+    fn from(text: &str) -> Lexeme {
+        Lexeme::new(Arc::from(text), 0..text.len())
+    }
+}
+
+impl From<String> for Lexeme {
+    fn from(text: String) -> Lexeme {
+        let length = text.len();
+        Lexeme::new(Arc::from(text), 0..length)
+    }
+}
+
+impl PartialEq for Lexeme {
+    fn eq(&self, other: &Lexeme) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for Lexeme {}
+
+impl PartialEq<str> for Lexeme {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for Lexeme {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl Debug for Lexeme {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl Display for Lexeme {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
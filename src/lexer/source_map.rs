@@ -0,0 +1,134 @@
+use std::iter;
+
+use super::span::{Span, advance};
+
+/// The `FileId` struct identifies a file in a `SourceMap`.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+pub struct FileId(pub usize);
+
+/// The `SourceMap` struct holds the text of the input files, and converts
+/// between byte offsets, positions and UTF-16 positions in the files.
+#[derive(Clone, Debug)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+    tab_width: usize,
+}
+
+impl SourceMap {
+    /// Constructs an empty source map, which uses the tab width to compute columns.
+    pub fn new(tab_width: usize) -> SourceMap {
+        SourceMap {
+            files: Vec::new(),
+            tab_width,
+        }
+    }
+
+    /// Adds a file to the source map, and returns its identifier.
+    pub fn add(&mut self, name: String, text: String) -> FileId {
+        let id = FileId(self.files.len());
+        self.files.push(SourceFile::new(name, text, self.tab_width));
+        id
+    }
+
+    /// Returns the file with the specified identifier.
+    pub fn file(&self, id: FileId) -> &SourceFile {
+        &self.files[id.0]
+    }
+
+    /// Returns the source text of the span.
+    pub fn text(&self, span: &Span) -> &str {
+        &self.file(span.file).text[span.range()]
+    }
+}
+
+/// The `SourceFile` struct describes an input file in a `SourceMap`.
+///
+/// Positions consist of a row and a column number, starting at 1. Columns are counted
+/// in characters, or in UTF-16 code units, as used by the Language Server Protocol.
+/// Byte offsets need to be at character boundaries.
+#[derive(Clone, Debug)]
+pub struct SourceFile {
+    pub name: String,
+    pub text: String,
+    tab_width: usize,
+    // Byte offsets of the first character of each line.
+    lines: Vec<usize>,
+}
+
+impl SourceFile {
+    /// Constructs a new source file.
+    fn new(name: String, text: String, tab_width: usize) -> SourceFile {
+        let lines = iter::once(0)
+            .chain(text.match_indices('\n').map(|(offset, _)| offset + 1))
+            .collect();
+
+        SourceFile {
+            name,
+            text,
+            tab_width,
+            lines,
+        }
+    }
+
+    /// Returns the position of the byte offset.
+    pub fn position(&self, offset: usize) -> (usize, usize) {
+        let (row, start) = self.line(offset);
+
+        self.text[start..offset]
+            .chars()
+            .fold((row, 1), |position, ch| {
+                advance(position, ch, self.tab_width)
+            })
+    }
+
+    /// Returns the byte offset of the position, if it exists in the file.
+    pub fn offset(&self, position: (usize, usize)) -> Option<usize> {
+        self.find_offset(position, |column, ch| {
+            advance((1, column), ch, self.tab_width).1
+        })
+    }
+
+    /// Returns the position of the byte offset, where the column is counted in UTF-16 code units.
+    pub fn utf16_position(&self, offset: usize) -> (usize, usize) {
+        let (row, start) = self.line(offset);
+        (row, self.text[start..offset].encode_utf16().count() + 1)
+    }
+
+    /// Returns the byte offset of the position, where the column is counted in UTF-16 code units.
+    pub fn utf16_offset(&self, position: (usize, usize)) -> Option<usize> {
+        self.find_offset(position, |column, ch| column + ch.len_utf16())
+    }
+
+    /// Returns the row and the byte offset of the line containing the byte offset.
+    fn line(&self, offset: usize) -> (usize, usize) {
+        let row = self.lines.partition_point(|&start| start <= offset);
+        (row, self.lines[row - 1])
+    }
+
+    /// Returns the byte offset of the position, where `next` returns the column
+    /// following a character. The end of a line is also a valid position.
+    fn find_offset(
+        &self,
+        position: (usize, usize),
+        next: impl Fn(usize, char) -> usize,
+    ) -> Option<usize> {
+        let (row, column) = position;
+        let start = *self.lines.get(row.checked_sub(1)?)?;
+        let mut current = 1;
+
+        for (idx, ch) in self.text[start..].char_indices() {
+            if current == column {
+                return Some(start + idx);
+            }
+
+            // The column is inside a tab, or after the end of the line.
+            if current > column || ch == '\n' {
+                return None;
+            }
+
+            current = next(current, ch);
+        }
+
+        (current == column).then_some(self.text.len())
+    }
+}
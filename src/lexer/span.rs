@@ -1,11 +1,20 @@
 use std::fmt::{self, Display, Formatter};
+use std::ops::Range;
+
+use super::source_map::FileId;
 
 /// The `Span` struct denotes the location of a `Token` in a source file.
-/// The position is indicated by a row and a column number.
+///
+/// The position is indicated by a row and a column number, where the end position
+/// is the last character of the token. The byte offset and the length in bytes
+/// locate the token in the text of the file.
 #[derive(Clone, Copy, Debug, Default)]
 pub struct Span {
+    pub file: FileId,
     pub start: (usize, usize),
     pub end: (usize, usize),
+    pub offset: usize,
+    pub len: usize,
 }
 
 impl Span {
@@ -14,13 +23,30 @@ impl Span {
         Span {
             start: position,
             end: position,
+            ..Span::default()
+        }
+    }
+
+    /// Constructs an empty span at the specified position and byte offset.
+    pub fn point(file: FileId, position: (usize, usize), offset: usize) -> Span {
+        Span {
+            file,
+            start: position,
+            end: position,
+            offset,
+            len: 0,
         }
     }
+
+    /// Returns the range of bytes in the source file.
+    pub fn range(&self) -> Range<usize> {
+        self.offset..self.offset + self.len
+    }
 }
 
 impl Display for Span {
     fn fmt(&self, f: &mut Formatter) -> fmt::Result {
-        let Span { start, end } = self;
+        let Span { start, end, .. } = self;
 
         if start.0 == end.0 {
             if start.1 == end.1 {
@@ -33,3 +59,15 @@ impl Display for Span {
         write!(f, "{}:{}-{}:{}", start.0, start.1, end.0, end.1)
     }
 }
+
+/// Advances the position in the file based on the current character.
+/// A tab advances the column to the next multiple of the tab width.
+pub fn advance(position: (usize, usize), ch: char, tab_width: usize) -> (usize, usize) {
+    let (row, column) = position;
+
+    match ch {
+        '\n' => (row + 1, 1),
+        '\t' => (row, column + tab_width - (column - 1) % tab_width),
+        _ => (row, column + 1),
+    }
+}
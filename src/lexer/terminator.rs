@@ -0,0 +1,65 @@
+use crate::grammar::{Grammar, Symbol, Terminator};
+
+use super::{Span, Token, span_after};
+
+/// The `State` struct inserts synthetic terminator tokens at line breaks.
+///
+/// A terminator is inserted if the line ends with one of the `after` symbols,
+/// and the first token of the next line can follow the terminator, i.e. it starts
+/// a new statement.
+pub struct State<'a> {
+    terminator: &'a Terminator,
+    grammar: &'a Grammar,
+    successors: Vec<usize>,
+    line_break: bool,
+    last_token: Option<(usize, Span)>,
+}
+
+impl<'a> State<'a> {
+    /// Constructs a new state for the terminator.
+    pub fn new(grammar: &'a Grammar, terminator: &'a Terminator) -> State<'a> {
+        State {
+            terminator,
+            grammar,
+            successors: grammar.successors(terminator.symbol),
+            line_break: false,
+            last_token: None,
+        }
+    }
+
+    /// Appends the token to the output, preceded by a terminator if necessary.
+    pub fn push(&mut self, token: Token, output: &mut Vec<Token>) {
+        if self.grammar.symbol(token.symbol).is_ignored() {
+            self.line_break |= token.lexeme.contains('\n');
+            output.push(token);
+            return;
+        }
+
+        if self.line_break && self.successors.contains(&token.symbol) {
+            self.insert(output);
+        }
+
+        self.line_break = false;
+        let tab_width = self.grammar.lexicon.tab_width;
+        self.last_token = Some((token.symbol, span_after(&token, tab_width)));
+        output.push(token);
+    }
+
+    /// Appends a terminator to the output at the end of the file, if necessary.
+    pub fn finish(&mut self, output: &mut Vec<Token>) {
+        // The end of the file also ends the last line.
+        if self.successors.contains(&Symbol::End.id()) {
+            self.insert(output);
+        }
+    }
+
+    /// Appends a terminator token if the last token can end a line.
+    fn insert(&self, output: &mut Vec<Token>) {
+        match self.last_token {
+            Some((symbol, span)) if self.terminator.after.contains(&symbol) => {
+                output.push(Token::synthetic(self.terminator.symbol, span));
+            }
+            _ => {}
+        }
+    }
+}
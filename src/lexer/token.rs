@@ -2,23 +2,56 @@ use std::fmt::{self, Display, Formatter};
 
 use crate::grammar::Symbol;
 
+use super::lexeme::Lexeme;
 use super::span::Span;
+use super::value::Value;
 
 /// The `Token` struct describes an element in the input file.
+///
+/// Synthetic tokens are inserted by the lexer or the parser,
+/// and don't correspond to any text in the input file.
+///
+/// Ignored tokens preceding the token are its leading trivia. Ignored tokens following
+/// the token on the same line, including the line break, are its trailing trivia.
+///
+/// Tokens with a value kind in the grammar also have a decoded value.
+///
+/// The lexeme shares the source text, so cloning a token doesn't copy its text.
 #[derive(Clone, Debug)]
 pub struct Token {
     pub symbol: usize,
-    pub lexeme: String,
+    pub lexeme: Lexeme,
     pub span: Span,
+    pub value: Option<Value>,
+    pub synthetic: bool,
+    pub leading: Vec<Token>,
+    pub trailing: Vec<Token>,
 }
 
 impl Token {
     /// Constructs a new token.
-    pub fn new(symbol: usize, lexeme: String, span: Span) -> Token {
+    pub fn new(symbol: usize, lexeme: Lexeme, span: Span) -> Token {
         Token {
             symbol,
             lexeme,
             span,
+            value: None,
+            synthetic: false,
+            leading: Vec::new(),
+            trailing: Vec::new(),
+        }
+    }
+
+    /// Constructs a synthetic token with an empty lexeme.
+    pub fn synthetic(symbol: usize, span: Span) -> Token {
+        Token {
+            symbol,
+            lexeme: Lexeme::default(),
+            span,
+            value: None,
+            synthetic: true,
+            leading: Vec::new(),
+            trailing: Vec::new(),
         }
     }
 
@@ -26,8 +59,12 @@ impl Token {
     pub fn end() -> Token {
         Token {
             symbol: Symbol::End.id(),
-            lexeme: Symbol::End.name(),
+            lexeme: Symbol::End.name().into(),
             span: Span::default(),
+            value: None,
+            synthetic: true,
+            leading: Vec::new(),
+            trailing: Vec::new(),
         }
     }
 
@@ -35,8 +72,12 @@ impl Token {
     pub fn null() -> Token {
         Token {
             symbol: Symbol::Null.id(),
-            lexeme: Symbol::Null.name(),
+            lexeme: Symbol::Null.name().into(),
             span: Span::default(),
+            value: None,
+            synthetic: true,
+            leading: Vec::new(),
+            trailing: Vec::new(),
         }
     }
 
@@ -44,6 +85,37 @@ impl Token {
     pub fn last(&self) -> Option<char> {
         self.lexeme.chars().last()
     }
+
+    /// Returns the length of the source text, including the trivia, in bytes.
+    pub fn width(&self) -> usize {
+        let trivia: usize = self
+            .leading
+            .iter()
+            .chain(&self.trailing)
+            .map(|trivia| trivia.lexeme.len())
+            .sum();
+
+        if self.synthetic {
+            trivia
+        } else {
+            trivia + self.lexeme.len()
+        }
+    }
+
+    /// Appends the source text of the token, including its trivia, to `text`.
+    pub fn unparse(&self, text: &mut String) {
+        for trivia in &self.leading {
+            text.push_str(&trivia.lexeme);
+        }
+
+        if !self.synthetic {
+            text.push_str(&self.lexeme);
+        }
+
+        for trivia in &self.trailing {
+            text.push_str(&trivia.lexeme);
+        }
+    }
 }
 
 impl Display for Token {
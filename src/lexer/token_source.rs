@@ -0,0 +1,99 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io::Read;
+use std::{slice, vec};
+
+use crate::grammar::{Grammar, Symbol};
+
+use super::{Error, Lexer, Span, Token};
+
+/// The `TokenSource` trait describes a lexer, which produces the tokens for the parsers.
+pub trait TokenSource {
+    /// Returns the next token, or `None` at the end of the input.
+    fn next_token(&mut self) -> Result<Option<Token>, Error>;
+
+    /// Returns all remaining tokens. Sources holding the tokens in memory return them
+    /// without copying.
+    fn remaining(&mut self) -> Result<Cow<'_, [Token]>, Error> {
+        let mut tokens = Vec::new();
+
+        while let Some(token) = self.next_token()? {
+            tokens.push(token);
+        }
+
+        Ok(Cow::Owned(tokens))
+    }
+}
+
+impl TokenSource for slice::Iter<'_, Token> {
+    fn next_token(&mut self) -> Result<Option<Token>, Error> {
+        Ok(self.next().cloned())
+    }
+
+    fn remaining(&mut self) -> Result<Cow<'_, [Token]>, Error> {
+        let tokens = self.as_slice();
+        *self = tokens[tokens.len()..].iter();
+        Ok(Cow::Borrowed(tokens))
+    }
+}
+
+impl TokenSource for vec::IntoIter<Token> {
+    fn next_token(&mut self) -> Result<Option<Token>, Error> {
+        Ok(self.next())
+    }
+}
+
+impl<R: Read> TokenSource for Lexer<'_, R> {
+    fn next_token(&mut self) -> Result<Option<Token>, Error> {
+        // The tokens with values that cannot be decoded are still parsed.
+        loop {
+            match self.next() {
+                Some(Err(error)) if error.is_value() => continue,
+                result => return result.transpose(),
+            }
+        }
+    }
+}
+
+/// The `NamedTokens` struct adapts an external lexer, which produces the kind name,
+/// the lexeme and the span of each token. The kinds are mapped onto the terminal
+/// symbols of the grammar by name, and the kinds of ignored tokens are skipped.
+pub struct NamedTokens<I> {
+    tokens: I,
+    symbols: HashMap<String, (usize, bool)>,
+}
+
+impl<I> NamedTokens<I> {
+    /// Constructs a new adapter for the grammar.
+    pub fn new(tokens: I, grammar: &Grammar) -> NamedTokens<I> {
+        let symbols = grammar
+            .symbols
+            .iter()
+            .filter_map(|symbol| match symbol {
+                Symbol::Terminal(id, name) => Some((name.clone(), (*id, false))),
+                Symbol::Ignored(id, name) => Some((name.clone(), (*id, true))),
+                _ => None,
+            })
+            .collect();
+
+        NamedTokens { tokens, symbols }
+    }
+}
+
+impl<I, K> TokenSource for NamedTokens<I>
+where
+    I: Iterator<Item = (K, String, Span)>,
+    K: AsRef<str>,
+{
+    fn next_token(&mut self) -> Result<Option<Token>, Error> {
+        for (kind, lexeme, span) in self.tokens.by_ref() {
+            match self.symbols.get(kind.as_ref()) {
+                Some(&(_, true)) => continue,
+                Some(&(symbol, false)) => return Ok(Some(Token::new(symbol, lexeme.into(), span))),
+                None => return Err(Error::Kind(kind.as_ref().to_owned(), span)),
+            }
+        }
+
+        Ok(None)
+    }
+}
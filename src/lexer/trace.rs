@@ -0,0 +1,27 @@
+use crate::grammar::Match;
+
+/// The `Trace` struct records the decisions of the lexer for the token
+/// at the byte offset in the input file.
+#[derive(Clone, Debug)]
+pub struct Trace {
+    pub offset: usize,
+    pub steps: Vec<Step>,
+}
+
+/// The `Step` struct records a single decision: the text tried by the matchers,
+/// and the match type of each matcher in order of precedence.
+///
+/// With the longest match policy, there is a step for each prefix of the token.
+/// With the first match policy, there is a single step with the matched text.
+#[derive(Clone, Debug)]
+pub struct Step {
+    pub text: String,
+    pub matches: Vec<(usize, Match)>,
+}
+
+impl Step {
+    /// Constructs a new step.
+    pub fn new(text: String, matches: Vec<(usize, Match)>) -> Step {
+        Step { text, matches }
+    }
+}
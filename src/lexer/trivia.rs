@@ -0,0 +1,114 @@
+use std::mem;
+
+use crate::grammar::Grammar;
+
+use super::span::advance;
+use super::{Span, Token};
+
+/// The `State` struct attaches ignored tokens to the surrounding tokens as trivia.
+///
+/// Trivia on the same line as the previous token, up to and including the first
+/// line break, is its trailing trivia. An ignored token which continues after the
+/// line break is split there. The remaining trivia is the leading trivia of the
+/// next token. Synthetic tokens don't have trivia. The last token represents
+/// the end of the file, and holds the trivia at the end of the file.
+pub struct State<'a> {
+    grammar: &'a Grammar,
+    leading: Vec<Token>,
+    // The token which can still receive trailing trivia, followed by synthetic tokens.
+    held: Vec<Token>,
+}
+
+impl<'a> State<'a> {
+    /// Constructs a new state.
+    pub fn new(grammar: &'a Grammar) -> State<'a> {
+        State {
+            grammar,
+            leading: Vec::new(),
+            held: Vec::new(),
+        }
+    }
+
+    /// Attaches the ignored token as trivia, or appends the token to the output
+    /// once its trailing trivia is complete.
+    pub fn push(&mut self, mut token: Token, output: &mut Vec<Token>) {
+        if self.grammar.symbol(token.symbol).is_ignored() {
+            if self.held.is_empty() {
+                self.leading.push(token);
+                return;
+            }
+
+            // The trailing trivia ends with a line break.
+            match token.lexeme.find('\n') {
+                Some(idx) => {
+                    let (trailing, leading) = self.split(token, idx + 1);
+                    self.held[0].trailing.push(trailing);
+                    self.leading.extend(leading);
+                    output.append(&mut self.held);
+                }
+                None => self.held[0].trailing.push(token),
+            }
+
+            return;
+        }
+
+        if token.synthetic {
+            if self.held.is_empty() {
+                output.push(token);
+            } else {
+                self.held.push(token);
+            }
+
+            return;
+        }
+
+        output.append(&mut self.held);
+        token.leading = mem::take(&mut self.leading);
+        self.held.push(token);
+    }
+
+    /// Splits the ignored token after the line break at the byte index. The second part
+    /// is `None` if the line break is the last character of the token.
+    fn split(&self, token: Token, mid: usize) -> (Token, Option<Token>) {
+        if mid == token.lexeme.len() {
+            return (token, None);
+        }
+
+        let (first, second) = token.lexeme.split_at(mid);
+        let tab_width = self.grammar.lexicon.tab_width;
+
+        // The line break is the last character of the first part.
+        let end = (first.chars())
+            .take_while(|&ch| ch != '\n')
+            .fold(token.span.start, |position, ch| {
+                advance(position, ch, tab_width)
+            });
+
+        let first_span = Span {
+            end,
+            len: mid,
+            ..token.span
+        };
+
+        let second_span = Span {
+            start: (end.0 + 1, 1),
+            offset: token.span.offset + mid,
+            len: token.span.len - mid,
+            ..token.span
+        };
+
+        let first = Token::new(token.symbol, first, first_span);
+        let second = Token::new(token.symbol, second, second_span);
+        (first, Some(second))
+    }
+
+    /// Appends the remaining tokens and the token representing the end of the file.
+    pub fn finish(&mut self, end: Span, output: &mut Vec<Token>) {
+        output.append(&mut self.held);
+
+        let mut token = Token::end();
+        token.span = end;
+        token.leading = mem::take(&mut self.leading);
+        output.push(token);
+    }
+}
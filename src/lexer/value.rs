@@ -0,0 +1,137 @@
+use crate::grammar::ValueKind;
+
+/// The `Value` enum describes the decoded value of a token.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Integer(i64),
+    Float(f64),
+    String(String),
+    Char(char),
+    Boolean(bool),
+    Identifier(String),
+}
+
+/// Decodes the lexeme into a value of the specified kind.
+/// If the lexeme cannot be decoded, the reason is returned as an error.
+pub fn decode(kind: ValueKind, lexeme: &str) -> Result<Value, String> {
+    match kind {
+        ValueKind::Integer => decode_integer(lexeme).map(Value::Integer),
+        ValueKind::Float => {
+            let value: f64 = match lexeme.replace('_', "").parse() {
+                Ok(value) => value,
+                Err(error) => return Err(error.to_string()),
+            };
+
+            if !value.is_finite() {
+                return Err("number too large to fit in target type".to_owned());
+            }
+
+            Ok(Value::Float(value))
+        }
+        ValueKind::String => decode_quoted(lexeme).map(Value::String),
+        ValueKind::Char => {
+            let text = decode_quoted(lexeme)?;
+            let mut chars = text.chars();
+
+            match (chars.next(), chars.next()) {
+                (Some(ch), None) => Ok(Value::Char(ch)),
+                _ => Err("character literal must contain one character".to_owned()),
+            }
+        }
+        ValueKind::Boolean => match lexeme {
+            "true" => Ok(Value::Boolean(true)),
+            "false" => Ok(Value::Boolean(false)),
+            _ => Err("invalid boolean literal".to_owned()),
+        },
+        ValueKind::Identifier => Ok(Value::Identifier(lexeme.to_owned())),
+    }
+}
+
+/// Decodes an integer with an optional radix prefix. Underscores are ignored.
+fn decode_integer(lexeme: &str) -> Result<i64, String> {
+    let text = lexeme.replace('_', "");
+
+    let (radix, digits) = match text.get(..2) {
+        Some("0x" | "0X") => (16, &text[2..]),
+        Some("0o" | "0O") => (8, &text[2..]),
+        Some("0b" | "0B") => (2, &text[2..]),
+        // A leading zero is an octal prefix in some languages.
+        _ if text.len() > 1 && text.starts_with('0') => {
+            return Err("leading zeros are not supported".to_owned());
+        }
+        _ => (10, text.as_str()),
+    };
+
+    i64::from_str_radix(digits, radix).map_err(|error| error.to_string())
+}
+
+/// Removes the quotes around the lexeme, and decodes the escape sequences.
+fn decode_quoted(lexeme: &str) -> Result<String, String> {
+    let quote = match lexeme.chars().next() {
+        Some(quote @ ('"' | '\'')) => quote,
+        _ => return Err("missing opening quote".to_owned()),
+    };
+
+    let text = &lexeme[1..];
+    let mut chars = text.chars();
+    let mut result = String::new();
+
+    loop {
+        let ch = match chars.next() {
+            Some(ch) => ch,
+            None => return Err("missing closing quote".to_owned()),
+        };
+
+        if ch == quote {
+            break;
+        }
+
+        if ch != '\\' {
+            result.push(ch);
+            continue;
+        }
+
+        let escaped = match chars.next() {
+            Some('n') => '\n',
+            Some('r') => '\r',
+            Some('t') => '\t',
+            Some('0') if chars.clone().next().is_some_and(|ch| ch.is_ascii_digit()) => {
+                return Err("octal escape sequences are not supported".to_owned());
+            }
+            Some('0') => '\0',
+            Some(ch @ ('\\' | '\'' | '"')) => ch,
+            Some('x') => {
+                let digits: String = chars.by_ref().take(2).collect();
+                decode_code_point(&digits, 0x7F)?
+            }
+            Some('u') => {
+                if chars.next() != Some('{') {
+                    return Err("invalid unicode escape".to_owned());
+                }
+
+                let digits: String = chars.by_ref().take_while(|&ch| ch != '}').collect();
+                decode_code_point(&digits, char::MAX as u32)?
+            }
+            Some(ch) => return Err(format!("unknown escape sequence '\\{}'", ch)),
+            None => return Err("missing closing quote".to_owned()),
+        };
+
+        result.push(escaped);
+    }
+
+    if !chars.as_str().is_empty() {
+        return Err("unexpected characters after closing quote".to_owned());
+    }
+
+    Ok(result)
+}
+
+/// Decodes a hexadecimal code point, which cannot be larger than `max`.
+fn decode_code_point(digits: &str, max: u32) -> Result<char, String> {
+    let code = match u32::from_str_radix(digits, 16) {
+        Ok(code) if code <= max => code,
+        _ => return Err(format!("invalid escape value '{}'", digits)),
+    };
+
+    char::from_u32(code).ok_or_else(|| format!("invalid escape value '{}'", digits))
+}
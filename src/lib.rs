@@ -0,0 +1,7 @@
+pub mod automaton;
+pub mod generator;
+pub mod grammar;
+pub mod lexer;
+pub mod parser;
+pub mod tree;
+pub mod util;
@@ -1,21 +1,30 @@
+use std::collections::HashMap;
 use std::fs;
+use std::io::{self, Read};
 use std::path::Path;
 use std::process;
+use std::thread;
 
-mod automaton;
-mod grammar;
-mod lexer;
-mod parser;
-mod util;
+use clap::ArgMatches;
+use serde_json::json;
+use syn::automaton::Automaton;
+use syn::generator::{self, Language, Method};
+use syn::grammar::{Grammar, Symbol};
+use syn::lexer::{FileId, Lexer, Step, Token, Trace};
+use syn::parser::Tables;
+use syn::util::{self, AsString};
+use syn::{grammar, lexer, parser};
 
-use automaton::Automaton;
-use util::AsString;
+/// The list of tokens and the list of diagnostics.
+type Tokens = (Vec<Token>, Vec<lexer::Error>);
 
 fn main() {
     let args = util::parse_args();
+    let command = args.subcommand();
+    let args = command.map_or(&args, |(_, args)| args);
 
     let filename = args.get_one::<String>("grammar").unwrap();
-    let mut grammar = match grammar::read_file(Path::new(filename)) {
+    let grammar = match grammar::read_file(Path::new(filename)) {
         Ok(grammar) => grammar,
         Err(error) => {
             eprintln!("Grammar file '{}' cannot be parsed: {}", filename, error);
@@ -28,21 +37,42 @@ fn main() {
         process::exit(1);
     }
 
+    if args.get_flag("warnings") {
+        for warning in grammar.analyze_tokens() {
+            eprintln!("Warning in grammar '{}': {}", grammar.name, warning);
+        }
+    }
+
+    match command {
+        Some(("tokens", _)) => return print_tokens(args, &grammar),
+        Some(("generate", _)) => return generate(args, &grammar),
+        _ => {}
+    }
+
     let filename = args.get_one::<String>("input").unwrap();
-    let tokens = match lexer::get_tokens(Path::new(filename), &grammar) {
-        Ok(tokens) => tokens,
+    // The input is read from the standard input if the file name is '-'.
+    let result = match filename.as_str() {
+        "-" => Lexer::new(io::stdin().lock(), FileId::default(), &grammar, true).tokenize(),
+        _ => lexer::recover_tokens(Path::new(filename), &grammar),
+    };
+
+    let (tokens, diagnostics) = match result {
+        Ok(result) => result,
         Err(error) => {
             eprintln!("Input file '{}' cannot be parsed: {}", filename, error);
             process::exit(1);
         }
     };
 
-    if tokens.is_empty() {
+    report_diagnostics(filename, &diagnostics);
+
+    // The input file is empty if there is only the $ symbol.
+    if tokens.iter().all(|token| token.symbol == Symbol::End.id()) {
         process::exit(0);
     }
 
     let rules = match args.get_one::<String>("parser").unwrap().as_str() {
-        "ll" => parser::parse_ll(&tokens, &grammar),
+        "ll" => parser::parse_ll(tokens.iter(), &grammar),
         "lr" => {
             let automaton = Automaton::new(&grammar, 0);
 
@@ -63,9 +93,10 @@ fn main() {
                 }
             }
 
-            parser::parse_lr(&tokens, &grammar, &data)
+            parser::parse_lr(tokens.iter(), &grammar, &data)
         }
-        "lllr" => parser::parse_lllr(&tokens, &mut grammar),
+        "lllr" => get_tables(args, &grammar)
+            .and_then(|tables| parser::parse_lllr(tokens.iter(), &grammar, &tables)),
         _ => Err(parser::Error::Internal),
     };
 
@@ -79,3 +110,249 @@ fn main() {
         println!("{}", rule.string(&grammar));
     }
 }
+
+/// Returns the LLLR tables of the grammar. The tables are saved next to the grammar file,
+/// and loaded instead of constructed, until the grammar changes.
+fn get_tables(args: &ArgMatches, grammar: &Grammar) -> Result<Tables, parser::Error> {
+    let filename = args.get_one::<String>("grammar").unwrap();
+    let path = Path::new(filename).with_extension("tables");
+    let cache = !args.get_flag("no-cache");
+
+    if cache {
+        let tables = fs::read(&path)
+            .ok()
+            .and_then(|bytes| Tables::from_bytes(grammar, &bytes).ok());
+
+        if let Some(tables) = tables {
+            return Ok(tables);
+        }
+    }
+
+    let tables = Tables::new(grammar, get_threads(args))?;
+
+    // The tables are written to a temporary file first,
+    // so parallel runs never read partially written tables.
+    if cache {
+        let temporary = path.with_extension(format!("tables.{}", process::id()));
+
+        let result = fs::write(&temporary, tables.to_bytes(grammar))
+            .and_then(|_| fs::rename(&temporary, &path));
+
+        if let Err(error) = result {
+            let _ = fs::remove_file(&temporary);
+            eprintln!("Unable to save to file '{}': {}", path.display(), error);
+        }
+    }
+
+    Ok(tables)
+}
+
+/// Returns the number of threads used to construct the LLLR tables.
+/// By default, all available threads are used.
+fn get_threads(args: &ArgMatches) -> usize {
+    args.get_one::<usize>("threads")
+        .copied()
+        .unwrap_or_else(|| thread::available_parallelism().map_or(1, |threads| threads.get()))
+}
+
+/// Generates a standalone parser for the grammar, and saves it to the output file.
+/// Without an output file, the parser is printed.
+fn generate(args: &ArgMatches, grammar: &Grammar) {
+    let language = Language::from_name(args.get_one::<String>("lang").unwrap()).unwrap();
+    let method = Method::from_name(args.get_one::<String>("parser").unwrap()).unwrap();
+
+    let source = match generator::generate(grammar, language, method, get_threads(args)) {
+        Ok(source) => source,
+        Err(error) => {
+            eprintln!("Grammar '{}' is not valid: {}", grammar.name, error);
+            process::exit(1);
+        }
+    };
+
+    match args.get_one::<String>("output") {
+        Some(output) => {
+            if let Err(error) = fs::write(Path::new(output), source) {
+                eprintln!("Unable to save to file '{}': {}", output, error);
+                process::exit(1);
+            }
+        }
+        None => print!("{}", source),
+    }
+}
+
+/// Prints the tokens of the input file with their symbol names, lexemes and spans,
+/// and optionally the ignored tokens and the match decisions of the lexer.
+fn print_tokens(args: &ArgMatches, grammar: &Grammar) {
+    let filename = args.get_one::<String>("input").unwrap();
+    let with_trace = args.get_flag("trace");
+
+    // The input is read from the standard input if the file name is '-'.
+    let result = match filename.as_str() {
+        "-" => {
+            let lexer = Lexer::new(io::stdin().lock(), FileId::default(), grammar, true);
+            read_tokens(lexer, with_trace)
+        }
+        _ => lexer::map_file(Path::new(filename)).and_then(|file| {
+            let lexer = Lexer::new(&file[..], FileId::default(), grammar, true);
+            read_tokens(lexer, with_trace)
+        }),
+    };
+
+    let ((tokens, diagnostics), trace) = match result {
+        Ok(result) => result,
+        Err(error) => {
+            eprintln!("Input file '{}' cannot be parsed: {}", filename, error);
+            process::exit(1);
+        }
+    };
+
+    let trace: HashMap<usize, Vec<Step>> = trace
+        .into_iter()
+        .map(|trace| (trace.offset, trace.steps))
+        .collect();
+
+    let mut entries = Vec::new();
+
+    for token in &tokens {
+        if args.get_flag("ignored") {
+            entries.extend(token.leading.iter());
+            entries.push(token);
+            entries.extend(token.trailing.iter());
+        } else {
+            entries.push(token);
+        }
+    }
+
+    // Synthetic tokens share the offset with the following token.
+    let steps = |token: &Token| match token.synthetic {
+        true => &[][..],
+        false => trace.get(&token.span.offset).map_or(&[][..], Vec::as_slice),
+    };
+
+    match args.get_one::<String>("format").unwrap().as_str() {
+        "json" => {
+            let values: Vec<_> = entries
+                .iter()
+                .map(|token| token_json(token, steps(token), with_trace, grammar))
+                .collect();
+
+            println!("{}", serde_json::to_string_pretty(&values).unwrap());
+        }
+        _ => {
+            for token in entries {
+                println!("{}", token_text(token, grammar));
+
+                for step in steps(token) {
+                    println!("{}", step_text(step, grammar));
+                }
+            }
+        }
+    }
+
+    report_diagnostics(filename, &diagnostics);
+}
+
+/// Prints the diagnostics of the lexer, and exits if any of them is an error. Values that
+/// cannot be decoded are warnings, because their tokens are still valid.
+fn report_diagnostics(filename: &str, diagnostics: &[lexer::Error]) {
+    for error in diagnostics {
+        match error.is_value() {
+            true => eprintln!("Warning in input file '{}': {}", filename, error),
+            false => eprintln!("Input file '{}' cannot be parsed: {}", filename, error),
+        }
+    }
+
+    if !diagnostics.iter().all(lexer::Error::is_value) {
+        process::exit(1);
+    }
+}
+
+/// Returns the tokens, the diagnostics and the match decisions of the lexer.
+fn read_tokens<R: Read>(
+    mut lexer: Lexer<R>,
+    with_trace: bool,
+) -> Result<(Tokens, Vec<Trace>), lexer::Error> {
+    if with_trace {
+        lexer.enable_trace();
+    }
+
+    let mut tokens = Vec::new();
+    let mut diagnostics = Vec::new();
+
+    for result in lexer.by_ref() {
+        match result {
+            Ok(token) => tokens.push(token),
+            Err(error @ lexer::Error::File(_)) => return Err(error),
+            Err(error) => diagnostics.push(error),
+        }
+    }
+
+    Ok(((tokens, diagnostics), lexer.take_trace()))
+}
+
+/// Formats the token as a line of text.
+fn token_text(token: &Token, grammar: &Grammar) -> String {
+    let name = grammar.symbol(token.symbol).name();
+    let span = token.span.to_string();
+
+    if token.synthetic {
+        return format!("{:<12} {}", span, name);
+    }
+
+    let lexeme = token.lexeme.escape_debug();
+    format!("{:<12} {:<16} \"{}\"", span, name, lexeme)
+}
+
+/// Formats the match decision as an indented line of text.
+fn step_text(step: &Step, grammar: &Grammar) -> String {
+    let matches: Vec<_> = (step.matches.iter())
+        .map(|(id, current_match)| format!("{} {}", grammar.symbol(*id).name(), current_match))
+        .collect();
+
+    let text = step.text.escape_debug();
+    format!("{:<12} \"{}\": {}", "", text, matches.join(", "))
+}
+
+/// Converts the token to a JSON value.
+fn token_json(
+    token: &Token,
+    steps: &[Step],
+    with_trace: bool,
+    grammar: &Grammar,
+) -> serde_json::Value {
+    let symbol = grammar.symbol(token.symbol);
+    let span = token.span;
+
+    let mut value = json!({
+        "symbol": symbol.name(),
+        "lexeme": if token.synthetic { "" } else { token.lexeme.as_str() },
+        "span": {
+            "file": span.file.0,
+            "start": [span.start.0, span.start.1],
+            "end": [span.end.0, span.end.1],
+            "offset": span.offset,
+            "len": span.len,
+        },
+        "ignored": matches!(symbol, Symbol::Ignored(..)),
+        "synthetic": token.synthetic,
+    });
+
+    if with_trace {
+        let steps: Vec<_> = (steps.iter())
+            .map(|step| {
+                let matches: Vec<_> = (step.matches.iter())
+                    .map(|(id, current_match)| {
+                        let name = grammar.symbol(*id).name();
+                        json!({ "symbol": name, "match": current_match.to_string() })
+                    })
+                    .collect();
+
+                json!({ "text": step.text, "matches": matches })
+            })
+            .collect();
+
+        value["trace"] = json!(steps);
+    }
+
+    value
+}
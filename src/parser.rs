@@ -1,25 +1,34 @@
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 use std::error;
 use std::fmt::{self, Display, Formatter};
 
-use crate::automaton::{Action, Automaton, Data, Item, Table};
+use crate::automaton::{Action, Compact, Data, Item, Table};
 use crate::grammar::{Grammar, Position, Symbol};
-use crate::lexer::Token;
+use crate::lexer::{self, Token, TokenSource};
 use crate::util;
 
+mod tables;
+
+pub use tables::Tables;
+
 /// Performs parsing using LLLR and returns the list of rules.
-pub fn parse_lllr(tokens: &[Token], grammar: &mut Grammar) -> Result<Vec<usize>, Error> {
-    let (parse_table, tables) = get_lllr_tables(grammar)?;
+pub fn parse_lllr(
+    mut source: impl TokenSource,
+    grammar: &Grammar,
+    tables: &Tables,
+) -> Result<Vec<usize>, Error> {
+    let parse_table = &tables.parse_table;
 
     let mut rules = Vec::new();
     let mut stack = vec![(Symbol::Start.id(), (0, 0))];
-    let mut input = get_input(tokens);
+    let mut input = Input::new(get_tokens(&mut source)?);
 
     while !stack.is_empty() && !input.is_empty() {
         let (symbol, position) = *stack.last().unwrap();
-        let token = input.front().cloned().unwrap();
+        let lookahead = input.symbol();
 
-        if let Some(&rule) = parse_table.get(&(symbol, token.symbol)) {
+        if let Some(rule) = parse_table.get(symbol, lookahead) {
             rules.push(rule);
             stack.pop();
 
@@ -33,12 +42,12 @@ pub fn parse_lllr(tokens: &[Token], grammar: &mut Grammar) -> Result<Vec<usize>,
         }
 
         // Start the LR parser if necessary.
-        if let Some(data) = tables.get(&position) {
-            let grammar = &data.grammar;
+        if let Some(data) = tables.get(position) {
+            let grammar = &*tables.grammar;
 
             let mut lr_rules = vec![Vec::new()];
             let mut lr_stack = vec![(symbol, 0)];
-            input.push_front(Token::end());
+            input.push_end();
 
             let is_valid = loop {
                 if lr_stack.is_empty() {
@@ -46,10 +55,10 @@ pub fn parse_lllr(tokens: &[Token], grammar: &mut Grammar) -> Result<Vec<usize>,
                 }
 
                 let state = lr_stack.last().unwrap().1;
-                let token = input.front().cloned().unwrap_or_else(Token::null);
+                let lookahead = input.symbol();
 
                 // Check if the LR parser can stop.
-                if let Some((item, tail)) = find_unique_item(grammar, data, state, &token) {
+                if let Some((item, tail)) = find_unique_item(grammar, data, state, lookahead) {
                     reduce_rules(&mut lr_rules, item.dot);
                     lr_rules.last_mut().unwrap().insert(0, item.rule);
 
@@ -64,34 +73,34 @@ pub fn parse_lllr(tokens: &[Token], grammar: &mut Grammar) -> Result<Vec<usize>,
                     break true;
                 }
 
-                let action = match data.action_table.get(&(state, token.symbol)) {
+                let action = match data.action_table.get(state, lookahead) {
                     Some(action) => action,
                     None => break false,
                 };
 
                 match action {
                     Action::Shift(state) => {
-                        lr_stack.push((token.symbol, *state));
+                        lr_stack.push((lookahead, state));
                         lr_rules.push(Vec::new());
-                        input.pop_front();
+                        input.pop();
                     }
                     Action::Reduce(rule) => {
-                        let rule = grammar.rule(*rule);
+                        let rule = grammar.rule(rule);
                         reduce_stack(&mut lr_stack, &rule.body)?;
                         reduce_rules(&mut lr_rules, rule.body.len());
                         lr_rules.last_mut().unwrap().insert(0, rule.id);
                         lr_rules.push(Vec::new());
 
                         let state = lr_stack.last().unwrap().1;
-                        let next_state = match data.goto_table.get(&(state, rule.head)) {
-                            Some(&next_state) => next_state,
+                        let next_state = match data.goto_table.get(state, rule.head) {
+                            Some(next_state) => next_state,
                             None => return Err(Error::Internal),
                         };
 
                         lr_stack.push((rule.head, next_state));
                     }
                     Action::Accept(rule) => {
-                        let body = grammar.rule(*rule).tail(1);
+                        let body = grammar.rule(rule).tail(1);
                         reduce_stack(&mut lr_stack, body)?;
                         reduce_rules(&mut lr_rules, body.len());
                         stack.truncate(stack.len() - body.len());
@@ -103,7 +112,7 @@ pub fn parse_lllr(tokens: &[Token], grammar: &mut Grammar) -> Result<Vec<usize>,
 
             if !is_valid {
                 return match next_token(&mut input, &grammar.symbols) {
-                    Some(token) => Err(Error::Parse(token)),
+                    Some(token) => Err(Error::Parse(Box::new(token))),
                     None => Err(Error::Eof),
                 };
             }
@@ -112,12 +121,12 @@ pub fn parse_lllr(tokens: &[Token], grammar: &mut Grammar) -> Result<Vec<usize>,
             continue;
         }
 
-        if symbol != token.symbol {
+        if symbol != lookahead {
             break;
         }
 
         stack.pop();
-        input.pop_front();
+        input.pop();
     }
 
     if !stack.is_empty() {
@@ -125,31 +134,25 @@ pub fn parse_lllr(tokens: &[Token], grammar: &mut Grammar) -> Result<Vec<usize>,
     }
 
     if let Some(token) = next_token(&mut input, &grammar.symbols) {
-        return Err(Error::Parse(token));
+        return Err(Error::Parse(Box::new(token)));
     }
 
     Ok(rules)
 }
 
 /// Performs parsing using LL(1) and returns the list of rules.
-pub fn parse_ll(tokens: &[Token], grammar: &Grammar) -> Result<Vec<usize>, Error> {
-    let parse_table = match get_ll_table(grammar, &HashSet::new()) {
-        Ok(parse_table) => parse_table,
-        Err(conflicts) => {
-            let symbol = grammar.symbol(conflicts[0]);
-            return Err(Error::Conflict(symbol.clone()));
-        }
-    };
+pub fn parse_ll(mut source: impl TokenSource, grammar: &Grammar) -> Result<Vec<usize>, Error> {
+    let parse_table = ll_table(grammar)?;
 
     let mut rules = Vec::new();
     let mut stack = vec![Symbol::Start.id()];
-    let mut input = get_input(tokens);
+    let mut input = Input::new(get_tokens(&mut source)?);
 
     while !stack.is_empty() && !input.is_empty() {
         let &symbol = stack.last().unwrap();
-        let token = input.front().unwrap();
+        let lookahead = input.symbol();
 
-        if let Some(&rule) = parse_table.get(&(symbol, token.symbol)) {
+        if let Some(rule) = parse_table.get(symbol, lookahead) {
             rules.push(rule);
             stack.pop();
 
@@ -162,12 +165,12 @@ pub fn parse_ll(tokens: &[Token], grammar: &Grammar) -> Result<Vec<usize>, Error
             continue;
         }
 
-        if symbol != token.symbol {
+        if symbol != lookahead {
             break;
         }
 
         stack.pop();
-        input.pop_front();
+        input.pop();
     }
 
     if !stack.is_empty() {
@@ -175,17 +178,21 @@ pub fn parse_ll(tokens: &[Token], grammar: &Grammar) -> Result<Vec<usize>, Error
     }
 
     if let Some(token) = next_token(&mut input, &grammar.symbols) {
-        return Err(Error::Parse(token));
+        return Err(Error::Parse(Box::new(token)));
     }
 
     Ok(rules)
 }
 
 /// Performs parsing using LR(1) and returns the list of rules.
-pub fn parse_lr(tokens: &[Token], grammar: &Grammar, data: &Data) -> Result<Vec<usize>, Error> {
+pub fn parse_lr(
+    mut source: impl TokenSource,
+    grammar: &Grammar,
+    data: &Data,
+) -> Result<Vec<usize>, Error> {
     let mut rules = Vec::new();
     let mut stack = vec![(Symbol::Start.id(), 0)];
-    let mut input = get_input(tokens);
+    let mut input = Input::new(get_tokens(&mut source)?);
 
     let is_valid = loop {
         if stack.is_empty() {
@@ -193,8 +200,8 @@ pub fn parse_lr(tokens: &[Token], grammar: &Grammar, data: &Data) -> Result<Vec<
         }
 
         let state = stack.last().unwrap().1;
-        let token = input.front().cloned().unwrap_or_else(Token::null);
-        let action = data.action_table.get(&(state, token.symbol));
+        let lookahead = input.symbol();
+        let action = data.action_table.get(state, lookahead);
 
         if action.is_none() {
             break false;
@@ -202,24 +209,24 @@ pub fn parse_lr(tokens: &[Token], grammar: &Grammar, data: &Data) -> Result<Vec<
 
         match action.unwrap() {
             Action::Shift(state) => {
-                stack.push((token.symbol, *state));
-                input.pop_front();
+                stack.push((lookahead, state));
+                input.pop();
             }
             Action::Reduce(rule) => {
-                let rule = grammar.rule(*rule);
+                let rule = grammar.rule(rule);
                 reduce_stack(&mut stack, &rule.body)?;
                 rules.push(rule.id);
 
                 let state = stack.last().unwrap().1;
-                let next_state = match data.goto_table.get(&(state, rule.head)) {
-                    Some(&next_state) => next_state,
+                let next_state = match data.goto_table.get(state, rule.head) {
+                    Some(next_state) => next_state,
                     None => return Err(Error::Internal),
                 };
 
                 stack.push((rule.head, next_state));
             }
             Action::Accept(rule) => {
-                let rule = grammar.rule(*rule);
+                let rule = grammar.rule(rule);
                 let mut body = rule.body.clone();
                 body.insert(0, rule.head);
 
@@ -237,7 +244,7 @@ pub fn parse_lr(tokens: &[Token], grammar: &Grammar, data: &Data) -> Result<Vec<
 
     if !is_valid {
         return match next_token(&mut input, &grammar.symbols) {
-            Some(token) => Err(Error::Parse(token)),
+            Some(token) => Err(Error::Parse(Box::new(token))),
             None => Err(Error::Eof),
         };
     }
@@ -246,6 +253,17 @@ pub fn parse_lr(tokens: &[Token], grammar: &Grammar, data: &Data) -> Result<Vec<
     Ok(rules)
 }
 
+/// Constructs the compressed LL(1) parse table, or returns the first conflict.
+pub fn ll_table(grammar: &Grammar) -> Result<Compact<usize>, Error> {
+    match get_ll_table(grammar, &HashSet::new()) {
+        Ok(parse_table) => Ok(Compact::new(&parse_table)),
+        Err(conflicts) => {
+            let symbol = grammar.symbol(conflicts[0]);
+            Err(Error::Conflict(symbol.clone()))
+        }
+    }
+}
+
 /// Constructs the LL parse table or returns the list of conflicts.
 fn get_ll_table(
     grammar: &Grammar,
@@ -279,144 +297,45 @@ fn get_ll_table(
     Ok(parse_table)
 }
 
-/// Constructs the LL and embedded LR tables.
-fn get_lllr_tables(
-    grammar: &mut Grammar,
-) -> Result<(Table<usize>, HashMap<Position, Data>), Error> {
-    let parse_table = get_ll_table(grammar, &HashSet::new());
-
-    let mut all_conflicts = HashSet::new();
-    let mut wrappers = HashMap::new();
-
-    // Find wrappers for conflicting symbols.
-    if let Err(conflicts) = parse_table {
-        all_conflicts.extend(conflicts);
-        let mut conflicts = all_conflicts.clone();
-
-        while !conflicts.is_empty() {
-            let mut new_conflicts = HashSet::new();
-
-            for rule in &grammar.rules {
-                // Ignore rules for conflicting symbols.
-                if all_conflicts.contains(&rule.head) {
-                    continue;
-                }
-
-                let mut idx = 0;
-
-                while idx < rule.body.len() {
-                    let symbol = rule.body[idx];
-
-                    // Ignore non-conflicting symbols.
-                    if !conflicts.contains(&symbol) {
-                        idx += 1;
-                        continue;
-                    }
-
-                    let mut symbols = Vec::new();
-                    let mut tail = rule.tail(idx).to_vec();
-
-                    // Find a wrapper with a valid LR automaton.
-                    let data = loop {
-                        if tail.is_empty() {
-                            break None;
-                        }
-
-                        symbols.push(tail.remove(0));
-                        let follow = grammar.first_follow(&tail, rule.head);
-
-                        if follow.is_empty() {
-                            let symbol = grammar.symbol(symbol);
-                            return Err(Error::Symbol(symbol.clone()));
-                        }
-
-                        let mut grammar = grammar.clone();
-                        let rule = grammar.wrap_symbols(&symbols, &follow);
-
-                        if let Ok(data) = Automaton::new(&grammar, rule).data() {
-                            break Some(data);
-                        }
-                    };
-
-                    if data.is_none() {
-                        if all_conflicts.insert(rule.head) {
-                            new_conflicts.insert(rule.head);
-                        }
-
-                        // Remove existing wrappers for the conflicting rule.
-                        wrappers.remove(&rule.head);
-                        break;
-                    }
-
-                    let wrapper = ((rule.id, idx), data.unwrap());
-                    idx += symbols.len();
-
-                    wrappers
-                        .entry(rule.head)
-                        .or_insert_with(Vec::new)
-                        .push(wrapper);
-                }
-            }
-
-            conflicts = new_conflicts;
-        }
-    }
-
-    // Construct the LL parse table, ignoring conflicting symbols.
-    let parse_table = match get_ll_table(grammar, &all_conflicts) {
-        Ok(parse_table) => parse_table,
-        Err(_) => return Err(Error::Internal),
-    };
-
-    // Construct embedded LR tables for conflicting symbols.
-    let tables: HashMap<Position, Data> = wrappers
-        .values()
-        .flatten()
-        .map(|(position, data)| (*position, data.clone()))
-        .collect();
-
-    Ok((parse_table, tables))
-}
-
 /// Finds a unique item in the current automaton state.
 /// Returns the item and the remaining symbols in the automaton.
 fn find_unique_item(
     grammar: &Grammar,
     data: &Data,
     state: usize,
-    token: &Token,
+    lookahead: usize,
 ) -> Option<(Item, Vec<(usize, Position)>)> {
-    let key = (state, token.symbol);
-
-    if let Some(action) = data.action_table.get(&key) {
-        if action.is_accept() {
-            return None;
-        }
+    if data
+        .action_table
+        .get(state, lookahead)
+        .is_some_and(|action| action.is_accept())
+    {
+        return None;
     }
 
-    if grammar.symbols[token.symbol].is_internal() {
+    if grammar.symbols[lookahead].is_internal() {
         return None;
     }
 
     // Find the unique item.
-    let mut from = match data.left_table.get(&key) {
-        Some(&item) => (state, item),
+    let mut from = match data.left_table.get(state, lookahead) {
+        Some(item) => (state, item),
         None => return None,
     };
 
-    let item = data.items[&from.1];
+    let item = &data.items[&from.1];
     let rule = grammar.rule(item.rule);
     let mut tail = rule.positions(item.dot);
     let mut current_rule = rule.id;
 
     // Follow item transitions to find the remaining symbols.
     loop {
-        from = match data.backtrack_table.get(&from) {
-            Some(&to) => to,
+        from = match data.backtrack_table.get(from.0, from.1) {
+            Some(to) => to,
             None => break,
         };
 
-        let item = data.items[&from.1];
+        let item = &data.items[&from.1];
         let rule = grammar.rule(item.rule);
 
         if rule.id != current_rule {
@@ -425,24 +344,89 @@ fn find_unique_item(
         }
     }
 
-    Some((item, tail))
+    Some((item.clone(), tail))
+}
+
+/// Returns the remaining tokens of the source.
+fn get_tokens(source: &mut impl TokenSource) -> Result<Cow<'_, [Token]>, Error> {
+    source.remaining().map_err(Error::Lexer)
 }
 
-/// Converts tokens to a parser input.
-fn get_input(tokens: &[Token]) -> VecDeque<Token> {
-    let mut input: VecDeque<Token> = tokens.iter().cloned().collect();
+/// The `Input` struct walks the parser input by index, without copying the tokens.
+/// The input starts with the $ symbol, and ends with it unless the tokens already do.
+/// The LLLR parser pushes additional $ symbols in front of the next token.
+struct Input<'a> {
+    tokens: Cow<'a, [Token]>,
+    end: Token,
+    // Index of the next token, where 0 is the leading $ symbol.
+    idx: usize,
+    len: usize,
+    ends: usize,
+}
 
-    input.push_front(Token::end());
-    input.push_back(Token::end());
+impl<'a> Input<'a> {
+    /// Constructs a new input from the tokens.
+    fn new(tokens: Cow<'a, [Token]>) -> Input<'a> {
+        // The lexer already ends the tokens with the $ symbol.
+        let is_closed = (tokens.last()).is_some_and(|token| token.symbol == Symbol::End.id());
+        let len = tokens.len() + if is_closed { 1 } else { 2 };
+
+        Input {
+            tokens,
+            end: Token::end(),
+            idx: 0,
+            len,
+            ends: 0,
+        }
+    }
+
+    /// Returns true if all tokens were consumed.
+    fn is_empty(&self) -> bool {
+        self.ends == 0 && self.idx == self.len
+    }
 
-    input
+    /// Returns the next token, or `None` at the end of the input.
+    fn front(&self) -> Option<&Token> {
+        if self.ends > 0 || self.idx == 0 {
+            return Some(&self.end);
+        }
+
+        match self.tokens.get(self.idx - 1) {
+            Some(token) => Some(token),
+            None if self.idx < self.len => Some(&self.end),
+            None => None,
+        }
+    }
+
+    /// Returns the symbol of the next token, or ϵ at the end of the input.
+    fn symbol(&self) -> usize {
+        self.front().map_or(Symbol::Null.id(), |token| token.symbol)
+    }
+
+    /// Consumes the next token.
+    fn pop(&mut self) {
+        if self.ends > 0 {
+            self.ends -= 1;
+        } else if self.idx < self.len {
+            self.idx += 1;
+        }
+    }
+
+    /// Pushes the $ symbol in front of the next token.
+    fn push_end(&mut self) {
+        self.ends += 1;
+    }
 }
 
-/// Returns the next input token, ignoring internal symbols.
-fn next_token(input: &mut VecDeque<Token>, symbols: &[Symbol]) -> Option<Token> {
-    input
-        .pop_front()
-        .filter(|token| !symbols[token.symbol].is_internal())
+/// Returns the next input token, ignoring internal symbols other than error tokens.
+fn next_token(input: &mut Input, symbols: &[Symbol]) -> Option<Token> {
+    let token = input
+        .front()
+        .filter(|token| token.symbol == Symbol::Error.id() || !symbols[token.symbol].is_internal())
+        .cloned();
+
+    input.pop();
+    token
 }
 
 /// Removes the rule symbols from the stack.
@@ -477,7 +461,8 @@ pub enum Error {
     Conflict(Symbol),
     Eof,
     Internal,
-    Parse(Token),
+    Lexer(lexer::Error),
+    Parse(Box<Token>),
     Symbol(Symbol),
 }
 
@@ -487,6 +472,7 @@ impl Display for Error {
             Self::Conflict(symbol) => write!(f, "Conflict in table for {}", symbol),
             Self::Eof => write!(f, "Unexpected end of file"),
             Self::Internal => write!(f, "Internal error"),
+            Self::Lexer(error) => write!(f, "{}", error),
             Self::Parse(token) => write!(f, "Unexpected token {}", token),
             Self::Symbol(symbol) => write!(f, "Cannot parse symbol {}", symbol),
         }
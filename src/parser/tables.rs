@@ -0,0 +1,318 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+
+use indexmap::IndexSet;
+
+use crate::automaton::{Compact, Data, Shared};
+use crate::grammar::{Derived, Grammar, Position};
+use crate::util::encoding::{self, Decoder, Encoder};
+
+use super::{Error, get_ll_table};
+
+/// Identifies the saved tables.
+const MAGIC: &[u8] = b"syn-tables";
+
+/// The version of the saved tables. It must change with the format,
+/// or with the construction of the tables.
+const VERSION: usize = 1;
+
+/// The wrapper rules, as the wrapped symbols and the follow set of each rule.
+type Wrappers = Vec<(Vec<usize>, Vec<usize>)>;
+
+/// The `Tables` struct contains the tables of the LLLR parser: the LL parse table,
+/// and the embedded LR tables of the wrapper rules, which are added to a derived grammar.
+#[derive(Clone, Debug)]
+pub struct Tables {
+    pub parse_table: Compact<usize>,
+    // The grammar with the wrapper rules of the embedded LR parsers.
+    pub grammar: Derived,
+    // The wrapper rules indexed by the symbol position they start at.
+    pub wrappers: HashMap<Position, usize>,
+    // The embedded LR tables indexed by the wrapper rule.
+    pub data: HashMap<usize, Data>,
+}
+
+impl Tables {
+    /// Constructs the LL and embedded LR tables. The automata of the wrapper rules
+    /// are constructed on the number of threads, and the tables don't depend on it.
+    pub fn new(grammar: &Grammar, threads: usize) -> Result<Tables, Error> {
+        let parse_table = get_ll_table(grammar, &HashSet::new());
+
+        let mut all_conflicts = HashSet::new();
+        let mut wrappers = HashMap::new();
+        let mut automata = Automata::new(grammar, threads);
+
+        // Find wrappers for conflicting symbols.
+        if let Err(conflicts) = parse_table {
+            all_conflicts.extend(conflicts);
+            let mut conflicts = all_conflicts.clone();
+
+            while !conflicts.is_empty() {
+                let mut new_conflicts = HashSet::new();
+
+                // Construct the shortest wrappers of the conflicting symbols in parallel.
+                // The remaining wrappers are constructed when they're needed.
+                let shortest = grammar
+                    .rules
+                    .iter()
+                    .filter(|rule| !all_conflicts.contains(&rule.head))
+                    .flat_map(|rule| {
+                        (0..rule.body.len())
+                            .filter(|&idx| conflicts.contains(&rule.body[idx]))
+                            .map(move |idx| {
+                                let follow = grammar.first_follow(rule.tail(idx + 1), rule.head);
+                                (vec![rule.body[idx]], follow)
+                            })
+                    })
+                    .filter(|(_, follow)| !follow.is_empty());
+
+                automata.construct(shortest.collect());
+
+                for rule in &grammar.rules {
+                    // Ignore rules for conflicting symbols.
+                    if all_conflicts.contains(&rule.head) {
+                        continue;
+                    }
+
+                    let mut idx = 0;
+
+                    while idx < rule.body.len() {
+                        let symbol = rule.body[idx];
+
+                        // Ignore non-conflicting symbols.
+                        if !conflicts.contains(&symbol) {
+                            idx += 1;
+                            continue;
+                        }
+
+                        let mut symbols = Vec::new();
+                        let mut tail = rule.tail(idx).to_vec();
+
+                        // Find a wrapper with a valid LR automaton.
+                        let wrapper = loop {
+                            if tail.is_empty() {
+                                break None;
+                            }
+
+                            symbols.push(tail.remove(0));
+                            let follow = grammar.first_follow(&tail, rule.head);
+
+                            if follow.is_empty() {
+                                let symbol = grammar.symbol(symbol);
+                                return Err(Error::Symbol(symbol.clone()));
+                            }
+
+                            let wrapper = automata.get(&symbols, &follow);
+
+                            if wrapper.is_some() {
+                                break wrapper;
+                            }
+                        };
+
+                        if wrapper.is_none() {
+                            if all_conflicts.insert(rule.head) {
+                                new_conflicts.insert(rule.head);
+                            }
+
+                            // Remove existing wrappers for the conflicting rule.
+                            wrappers.remove(&rule.head);
+                            break;
+                        }
+
+                        let wrapper = ((rule.id, idx), wrapper.unwrap());
+                        idx += symbols.len();
+
+                        wrappers
+                            .entry(rule.head)
+                            .or_insert_with(Vec::new)
+                            .push(wrapper);
+                    }
+                }
+
+                conflicts = new_conflicts;
+            }
+        }
+
+        // Construct the LL parse table, ignoring conflicting symbols.
+        let parse_table = match get_ll_table(grammar, &all_conflicts) {
+            Ok(parse_table) => parse_table,
+            Err(_) => return Err(Error::Internal),
+        };
+
+        let wrappers: HashMap<Position, usize> = wrappers.into_values().flatten().collect();
+
+        // Keep the embedded LR tables of the remaining wrappers.
+        let data = automata
+            .data
+            .into_iter()
+            .filter(|(rule, _)| wrappers.values().any(|wrapper| wrapper == rule))
+            .filter_map(|(rule, data)| Some((rule, data?)))
+            .collect();
+
+        Ok(Tables {
+            parse_table: Compact::new(&parse_table),
+            grammar: automata.grammar,
+            wrappers,
+            data,
+        })
+    }
+
+    /// Returns the tables in a binary format, which can be loaded for the grammar
+    /// the tables were constructed for. The format starts with its version and the hash
+    /// of the grammar, and ends with a checksum of the contents.
+    pub fn to_bytes(&self, grammar: &Grammar) -> Vec<u8> {
+        // The wrapper rules follow the rules of the base grammar.
+        let wrappers: Wrappers = self.grammar.rules[grammar.rules.len()..]
+            .iter()
+            .map(|rule| (rule.body[1..].to_vec(), rule.follow.clone()))
+            .collect();
+
+        let mut encoder = Encoder::new();
+        encoder.write_bytes(MAGIC);
+        encoder.write(&VERSION);
+        encoder.write(&grammar.content_hash());
+        encoder.write(&wrappers);
+        encoder.write(&self.parse_table);
+        encoder.write(&self.wrappers);
+        encoder.write(&self.data);
+
+        let mut bytes = encoder.into_bytes();
+        let checksum = encoding::hash(&bytes);
+        bytes.extend_from_slice(&checksum.to_le_bytes());
+        bytes
+    }
+
+    /// Loads the tables saved by `to_bytes` for the grammar, without constructing
+    /// any automata. The wrapper rules are added to the derived grammar again.
+    pub fn from_bytes(grammar: &Grammar, bytes: &[u8]) -> Result<Tables, encoding::Error> {
+        let (contents, checksum) = bytes.split_at(bytes.len().saturating_sub(8));
+
+        if !contents.starts_with(MAGIC) || checksum != encoding::hash(contents).to_le_bytes() {
+            return Err(encoding::Error::Invalid);
+        }
+
+        let mut decoder = Decoder::new(&contents[MAGIC.len()..]);
+        let version = decoder.read()?;
+
+        if version != VERSION {
+            return Err(encoding::Error::Version(version));
+        }
+
+        if decoder.read::<u64>()? != grammar.content_hash() {
+            return Err(encoding::Error::Grammar);
+        }
+
+        let mut derived = Derived::new(grammar);
+
+        for (symbols, follow) in decoder.read::<Wrappers>()? {
+            let rule = derived.rules.len();
+            let symbol_count = derived.symbols.len();
+
+            if symbols.is_empty() || symbols.iter().chain(&follow).any(|&id| id >= symbol_count) {
+                return Err(encoding::Error::Invalid);
+            }
+
+            // The rules are added in the same order, so they keep their IDs.
+            if derived.wrap_symbols(&symbols, &follow) != rule {
+                return Err(encoding::Error::Invalid);
+            }
+        }
+
+        let tables = Tables {
+            parse_table: decoder.read()?,
+            grammar: derived,
+            wrappers: decoder.read()?,
+            data: decoder.read()?,
+        };
+
+        let missing = (tables.wrappers.values()).any(|rule| !tables.data.contains_key(rule));
+
+        if !decoder.is_empty() || missing {
+            return Err(encoding::Error::Invalid);
+        }
+
+        Ok(tables)
+    }
+
+    /// Returns the embedded LR tables, which start at the symbol position.
+    pub fn get(&self, position: Position) -> Option<&Data> {
+        self.wrappers.get(&position).map(|rule| &self.data[rule])
+    }
+}
+
+/// The `Automata` struct constructs the automata of the wrapper rules, and keeps
+/// their tables. All wrapper rules are added to the same derived grammar, and
+/// the automata constructed on the same thread share the states.
+struct Automata {
+    grammar: Derived,
+    shared: Vec<Shared>,
+    // The tables of each wrapper rule, or `None` if its automaton has conflicts.
+    // Equal wrappers have the same rule, so each automaton is constructed once.
+    data: HashMap<usize, Option<Data>>,
+}
+
+impl Automata {
+    /// Constructs a new set of automata for the grammar.
+    fn new(grammar: &Grammar, threads: usize) -> Automata {
+        Automata {
+            grammar: Derived::new(grammar),
+            shared: vec![Shared::new(); threads.max(1)],
+            data: HashMap::new(),
+        }
+    }
+
+    /// Returns the rule of the wrapper, if its automaton doesn't have conflicts.
+    fn get(&mut self, symbols: &[usize], follow: &[usize]) -> Option<usize> {
+        let rule = self.grammar.wrap_symbols(symbols, follow);
+
+        let data = self.data.entry(rule).or_insert_with(|| {
+            let automaton = self.shared[0].automaton(&self.grammar, rule);
+            automaton.data().ok()
+        });
+
+        data.as_ref().map(|_| rule)
+    }
+
+    /// Constructs the automata of the wrappers on all threads.
+    fn construct(&mut self, wrappers: Vec<(Vec<usize>, Vec<usize>)>) {
+        let rules: IndexSet<usize> = wrappers
+            .iter()
+            .map(|(symbols, follow)| self.grammar.wrap_symbols(symbols, follow))
+            .filter(|rule| !self.data.contains_key(rule))
+            .collect();
+
+        let grammar = &*self.grammar;
+        let next = AtomicUsize::new(0);
+
+        // Each thread takes the next rule until none are left. The states are
+        // numbered in the same order in every automaton, so the tables don't
+        // depend on the thread they're constructed on.
+        let data: Vec<(usize, Option<Data>)> = thread::scope(|scope| {
+            let handles: Vec<_> = (self.shared.iter_mut())
+                .map(|shared| {
+                    let (rules, next) = (&rules, &next);
+
+                    scope.spawn(move || {
+                        let mut data = Vec::new();
+
+                        while let Some(&rule) =
+                            rules.get_index(next.fetch_add(1, Ordering::Relaxed))
+                        {
+                            let automaton = shared.automaton(grammar, rule);
+                            data.push((rule, automaton.data().ok()));
+                        }
+
+                        data
+                    })
+                })
+                .collect();
+
+            (handles.into_iter())
+                .flat_map(|handle| handle.join().unwrap())
+                .collect()
+        });
+
+        self.data.extend(data);
+    }
+}
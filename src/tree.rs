@@ -0,0 +1,150 @@
+use std::error;
+use std::fmt::{self, Display, Formatter};
+use std::sync::Arc;
+
+use crate::grammar::{Grammar, Rule, Symbol};
+use crate::lexer::Token;
+
+mod green;
+mod red;
+
+pub use green::{GreenElement, GreenNode};
+pub use red::{SyntaxElement, SyntaxNode, SyntaxToken};
+
+/// The `Derivation` enum describes the order of the rules produced by a parser.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Derivation {
+    /// A leftmost derivation, produced by the LL and LLLR parsers.
+    Leftmost,
+
+    /// A rightmost derivation, produced by the LR parser.
+    Rightmost,
+}
+
+/// Constructs a lossless syntax tree from the list of tokens and the rules produced by a parser.
+pub fn build(
+    tokens: &[Token],
+    rules: &[usize],
+    grammar: &Grammar,
+    derivation: Derivation,
+) -> Result<SyntaxNode, Error> {
+    let reverse = derivation == Derivation::Rightmost;
+
+    // Rightmost derivations expand the symbols from right to left.
+    let mut tokens: Vec<&Token> = tokens.iter().collect();
+    if !reverse {
+        tokens.reverse();
+    }
+
+    let mut rules = rules.iter().map(|&id| grammar.rule(id));
+    let mut stack = match rules.next() {
+        Some(rule) => vec![Frame::new(rule, reverse)],
+        None => return Err(Error::Eof),
+    };
+
+    let root = loop {
+        let frame = stack.last_mut().unwrap();
+
+        let symbol = match frame.symbols.pop() {
+            Some(symbol) => symbol,
+            None => {
+                let node = Arc::new(stack.pop().unwrap().finish(reverse));
+
+                match stack.last_mut() {
+                    Some(frame) => frame.children.push(GreenElement::Node(node)),
+                    None => break node,
+                }
+
+                continue;
+            }
+        };
+
+        if symbol == Symbol::Null.id() {
+            continue;
+        }
+
+        if grammar.symbol(symbol).is_nonterminal() {
+            let rule = match rules.next() {
+                Some(rule) if rule.head == symbol => rule,
+                Some(rule) => return Err(Error::Rule(rule.id)),
+                None => return Err(Error::Eof),
+            };
+
+            stack.push(Frame::new(rule, reverse));
+            continue;
+        }
+
+        // The $ symbols don't always correspond to a token.
+        let token = match tokens.last() {
+            Some(token) if token.symbol == symbol => tokens.pop().unwrap().clone(),
+            _ if symbol == Symbol::End.id() => Token::end(),
+            Some(&token) => return Err(Error::Token(Box::new(token.clone()))),
+            None => return Err(Error::Eof),
+        };
+
+        frame.children.push(GreenElement::Token(Arc::new(token)));
+    };
+
+    if let Some(rule) = rules.next() {
+        return Err(Error::Rule(rule.id));
+    }
+
+    if let Some(&token) = tokens.last() {
+        return Err(Error::Token(Box::new(token.clone())));
+    }
+
+    Ok(SyntaxNode::new_root(root))
+}
+
+/// The `Frame` struct describes a node under construction.
+struct Frame<'a> {
+    rule: &'a Rule,
+    // Remaining symbols of the rule, in reverse order of expansion.
+    symbols: Vec<usize>,
+    children: Vec<GreenElement>,
+}
+
+impl<'a> Frame<'a> {
+    /// Constructs a new frame for the rule.
+    fn new(rule: &'a Rule, reverse: bool) -> Frame<'a> {
+        let mut symbols = rule.body.clone();
+
+        if !reverse {
+            symbols.reverse();
+        }
+
+        Frame {
+            rule,
+            symbols,
+            children: Vec::new(),
+        }
+    }
+
+    /// Constructs the green node from the frame.
+    fn finish(mut self, reverse: bool) -> GreenNode {
+        if reverse {
+            self.children.reverse();
+        }
+
+        GreenNode::new(self.rule.head, Some(self.rule.id), self.children)
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Eof,
+    Rule(usize),
+    Token(Box<Token>),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Eof => write!(f, "Unexpected end of derivation"),
+            Self::Rule(rule) => write!(f, "Unexpected rule ({})", rule),
+            Self::Token(token) => write!(f, "Unexpected token {}", token),
+        }
+    }
+}
+
+impl error::Error for Error {}
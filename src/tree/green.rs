@@ -0,0 +1,65 @@
+use std::sync::Arc;
+
+use crate::lexer::Token;
+
+/// The `GreenNode` struct describes an immutable node of the syntax tree.
+///
+/// Green nodes don't know their parent or their position in the input file,
+/// so identical subtrees can be shared between trees, and cloning is cheap.
+#[derive(Clone, Debug)]
+pub struct GreenNode {
+    pub symbol: usize,
+    pub rule: Option<usize>,
+    pub children: Vec<GreenElement>,
+    // Length of the source text in bytes.
+    width: usize,
+}
+
+impl GreenNode {
+    /// Constructs a new green node.
+    pub fn new(symbol: usize, rule: Option<usize>, children: Vec<GreenElement>) -> GreenNode {
+        let width = children.iter().map(GreenElement::width).sum();
+
+        GreenNode {
+            symbol,
+            rule,
+            children,
+            width,
+        }
+    }
+
+    /// Returns the length of the source text in bytes.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Appends the source text of the node, including all trivia, to `text`.
+    pub fn unparse(&self, text: &mut String) {
+        for child in &self.children {
+            match child {
+                GreenElement::Node(node) => node.unparse(text),
+                GreenElement::Token(token) => token.unparse(text),
+            }
+        }
+    }
+}
+
+/// The `GreenElement` enum describes a child of a green node.
+#[derive(Clone, Debug)]
+pub enum GreenElement {
+    /// A nonterminal symbol.
+    Node(Arc<GreenNode>),
+
+    /// A terminal symbol with its trivia.
+    Token(Arc<Token>),
+}
+
+impl GreenElement {
+    /// Returns the length of the source text in bytes.
+    pub fn width(&self) -> usize {
+        match self {
+            Self::Node(node) => node.width(),
+            Self::Token(token) => token.width(),
+        }
+    }
+}
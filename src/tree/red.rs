@@ -0,0 +1,155 @@
+use std::sync::Arc;
+
+use crate::lexer::Token;
+
+use super::green::{GreenElement, GreenNode};
+
+/// The `SyntaxNode` struct is a view of a green node, which knows its parent
+/// and its byte offset in the input file. Cloning a syntax node is cheap.
+#[derive(Clone, Debug)]
+pub struct SyntaxNode(Arc<NodeData>);
+
+#[derive(Debug)]
+struct NodeData {
+    green: Arc<GreenNode>,
+    parent: Option<SyntaxNode>,
+    // Index of the node in its parent's children.
+    index: usize,
+    offset: usize,
+}
+
+impl SyntaxNode {
+    /// Constructs the root node of a syntax tree.
+    pub fn new_root(green: Arc<GreenNode>) -> SyntaxNode {
+        SyntaxNode(Arc::new(NodeData {
+            green,
+            parent: None,
+            index: 0,
+            offset: 0,
+        }))
+    }
+
+    /// Returns the underlying green node.
+    pub fn green(&self) -> &Arc<GreenNode> {
+        &self.0.green
+    }
+
+    /// Returns the symbol of the node.
+    pub fn symbol(&self) -> usize {
+        self.0.green.symbol
+    }
+
+    /// Returns the rule that derived the node, if any.
+    pub fn rule(&self) -> Option<usize> {
+        self.0.green.rule
+    }
+
+    /// Returns the parent of the node, if any.
+    pub fn parent(&self) -> Option<&SyntaxNode> {
+        self.0.parent.as_ref()
+    }
+
+    /// Returns the byte offset of the node in the input file.
+    pub fn offset(&self) -> usize {
+        self.0.offset
+    }
+
+    /// Returns the children of the node.
+    pub fn children(&self) -> Vec<SyntaxElement> {
+        let mut offset = self.offset();
+
+        self.0
+            .green
+            .children
+            .iter()
+            .enumerate()
+            .map(|(index, child)| {
+                let element = match child {
+                    GreenElement::Node(green) => {
+                        SyntaxElement::Node(SyntaxNode(Arc::new(NodeData {
+                            green: green.clone(),
+                            parent: Some(self.clone()),
+                            index,
+                            offset,
+                        })))
+                    }
+                    GreenElement::Token(token) => SyntaxElement::Token(SyntaxToken {
+                        token: token.clone(),
+                        parent: self.clone(),
+                        offset,
+                    }),
+                };
+
+                offset += child.width();
+                element
+            })
+            .collect()
+    }
+
+    /// Returns the node and all its descendant nodes in preorder.
+    pub fn descendants(&self) -> Vec<SyntaxNode> {
+        let mut nodes = Vec::new();
+        let mut stack = vec![self.clone()];
+
+        while let Some(node) = stack.pop() {
+            let children = node.children().into_iter().rev();
+
+            stack.extend(children.filter_map(|child| match child {
+                SyntaxElement::Node(node) => Some(node),
+                SyntaxElement::Token(_) => None,
+            }));
+
+            nodes.push(node);
+        }
+
+        nodes
+    }
+
+    /// Replaces the node with a new green node and returns the root of the new tree.
+    /// The original tree is not modified, and shares all unchanged nodes with the new tree.
+    pub fn replace(&self, green: GreenNode) -> SyntaxNode {
+        let mut green = Arc::new(green);
+        let mut node = self;
+
+        while let Some(parent) = node.parent() {
+            let mut children = parent.green().children.clone();
+            children[node.0.index] = GreenElement::Node(green);
+
+            let parent_green = parent.green();
+            green = Arc::new(GreenNode::new(
+                parent_green.symbol,
+                parent_green.rule,
+                children,
+            ));
+            node = parent;
+        }
+
+        SyntaxNode::new_root(green)
+    }
+
+    /// Returns the source text of the node, including all trivia.
+    pub fn unparse(&self) -> String {
+        let mut text = String::new();
+        self.0.green.unparse(&mut text);
+        text
+    }
+}
+
+/// The `SyntaxToken` struct is a view of a token in the syntax tree.
+#[derive(Clone, Debug)]
+pub struct SyntaxToken {
+    pub token: Arc<Token>,
+    pub parent: SyntaxNode,
+    // Byte offset of the token's leading trivia in the input file.
+    pub offset: usize,
+}
+
+/// The `SyntaxElement` enum describes a child of a syntax node.
+#[derive(Clone, Debug)]
+pub enum SyntaxElement {
+    /// A nonterminal symbol.
+    Node(SyntaxNode),
+
+    /// A terminal symbol with its trivia.
+    Token(SyntaxToken),
+}
@@ -1,7 +1,12 @@
-use clap::{Arg, ArgMatches, Command, crate_name, crate_version};
+use clap::{Arg, ArgAction, ArgMatches, Command, crate_name, crate_version, value_parser};
 
 use crate::grammar::Grammar;
 
+mod bitset;
+pub mod encoding;
+
+pub use bitset::BitSet;
+
 /// The `AsString` trait is used as an alternative to the `Display` trait,
 /// as it requires a `Grammar` argument to correctly format a struct.
 pub trait AsString {
@@ -18,29 +23,11 @@ impl<T: AsString> AsString for &T {
 pub fn parse_args() -> ArgMatches {
     Command::new(crate_name!())
         .version(crate_version!())
-        .arg(
-            Arg::new("input")
-                .value_name("INPUT")
-                .help("Input file name")
-                .required(true),
-        )
-        .arg(
-            Arg::new("grammar")
-                .long("grammar")
-                .short('g')
-                .value_name("FILE")
-                .help("Grammar file name")
-                .required(true),
-        )
-        .arg(
-            Arg::new("parser")
-                .help("Parser name")
-                .long("parser")
-                .short('p')
-                .value_name("NAME")
-                .value_parser(["lllr", "ll", "lr"])
-                .default_value("lllr"),
-        )
+        .args_conflicts_with_subcommands(true)
+        .subcommand_negates_reqs(true)
+        .arg(input_arg())
+        .arg(grammar_arg())
+        .arg(parser_arg())
         .arg(
             Arg::new("output")
                 .long("output")
@@ -48,9 +35,119 @@ pub fn parse_args() -> ArgMatches {
                 .value_name("FILE")
                 .help("Output file name for the LR automaton"),
         )
+        .arg(threads_arg())
+        .arg(
+            Arg::new("no-cache")
+                .long("no-cache")
+                .help("Don't load or save the LLLR tables next to the grammar file")
+                .action(ArgAction::SetTrue),
+        )
+        .arg(warnings_arg())
+        .subcommand(
+            Command::new("tokens")
+                .about("Prints the tokens of the input file")
+                .arg(input_arg())
+                .arg(grammar_arg())
+                .arg(
+                    Arg::new("ignored")
+                        .long("ignored")
+                        .short('i')
+                        .help("Include the ignored tokens")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("trace")
+                        .long("trace")
+                        .short('t')
+                        .help("Include the match decisions for each token")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .short('f')
+                        .value_name("FORMAT")
+                        .help("Output format")
+                        .value_parser(["text", "json"])
+                        .default_value("text"),
+                )
+                .arg(warnings_arg()),
+        )
+        .subcommand(
+            Command::new("generate")
+                .about("Generates a standalone parser for the grammar")
+                .arg(grammar_arg())
+                .arg(
+                    Arg::new("lang")
+                        .long("lang")
+                        .short('l')
+                        .value_name("LANGUAGE")
+                        .help("Language of the generated parser")
+                        .value_parser(["rust"])
+                        .default_value("rust"),
+                )
+                .arg(parser_arg())
+                .arg(
+                    Arg::new("output")
+                        .long("output")
+                        .short('o')
+                        .value_name("FILE")
+                        .help("Output file name for the generated parser"),
+                )
+                .arg(threads_arg())
+                .arg(warnings_arg()),
+        )
         .get_matches()
 }
 
+/// Returns the argument for the input file name.
+fn input_arg() -> Arg {
+    Arg::new("input")
+        .value_name("INPUT")
+        .help("Input file name, or - for the standard input")
+        .required(true)
+}
+
+/// Returns the argument for the grammar file name.
+fn grammar_arg() -> Arg {
+    Arg::new("grammar")
+        .long("grammar")
+        .short('g')
+        .value_name("FILE")
+        .help("Grammar file name")
+        .required(true)
+}
+
+/// Returns the argument for the parser name.
+fn parser_arg() -> Arg {
+    Arg::new("parser")
+        .help("Parser name")
+        .long("parser")
+        .short('p')
+        .value_name("NAME")
+        .value_parser(["lllr", "ll", "lr"])
+        .default_value("lllr")
+}
+
+/// Returns the argument for the number of threads.
+fn threads_arg() -> Arg {
+    Arg::new("threads")
+        .long("threads")
+        .short('j')
+        .value_name("COUNT")
+        .help("Number of threads for the LLLR tables")
+        .value_parser(value_parser!(usize))
+}
+
+/// Returns the flag for the warnings about the token definitions.
+fn warnings_arg() -> Arg {
+    Arg::new("warnings")
+        .long("warnings")
+        .short('w')
+        .help("Print warnings about the token definitions")
+        .action(ArgAction::SetTrue)
+}
+
 /// Returns the index of an element in a vector.
 pub fn get_index<T>(vec: &[T], value: T) -> usize
 where
@@ -0,0 +1,173 @@
+use std::fmt::{self, Debug, Formatter};
+use std::hash::{Hash, Hasher};
+
+use super::encoding::{Decoder, Encode, Encoder, Error};
+
+/// Number of bits in a word.
+const WORD_BITS: usize = u64::BITS as usize;
+
+/// The `BitSet` struct is a dense set of small integers, such as symbol IDs.
+/// The set grows when a larger integer is inserted.
+#[derive(Clone, Default)]
+pub struct BitSet {
+    words: Vec<u64>,
+}
+
+impl BitSet {
+    /// Constructs a new empty set.
+    pub fn new() -> BitSet {
+        BitSet { words: Vec::new() }
+    }
+
+    /// Constructs a new empty set with space for the integers below `capacity`.
+    pub fn with_capacity(capacity: usize) -> BitSet {
+        BitSet {
+            words: vec![0; capacity.div_ceil(WORD_BITS)],
+        }
+    }
+
+    /// Returns `true` if the set contains the value.
+    pub fn contains(&self, value: usize) -> bool {
+        (self.words.get(value / WORD_BITS))
+            .is_some_and(|word| word & (1 << (value % WORD_BITS)) != 0)
+    }
+
+    /// Adds the value to the set, and returns `true` if it was not present.
+    pub fn insert(&mut self, value: usize) -> bool {
+        let idx = value / WORD_BITS;
+
+        if idx >= self.words.len() {
+            self.words.resize(idx + 1, 0);
+        }
+
+        let mask = 1 << (value % WORD_BITS);
+        let is_new = self.words[idx] & mask == 0;
+        self.words[idx] |= mask;
+        is_new
+    }
+
+    /// Removes the value from the set, and returns `true` if it was present.
+    pub fn remove(&mut self, value: usize) -> bool {
+        let mask = 1 << (value % WORD_BITS);
+
+        match self.words.get_mut(value / WORD_BITS) {
+            Some(word) if *word & mask != 0 => {
+                *word &= !mask;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Adds all values of the other set, and returns `true` if the set changed.
+    pub fn union_with(&mut self, other: &BitSet) -> bool {
+        if other.words.len() > self.words.len() {
+            self.words.resize(other.words.len(), 0);
+        }
+
+        let mut changed = false;
+
+        for (word, &other) in self.words.iter_mut().zip(&other.words) {
+            changed |= other & !*word != 0;
+            *word |= other;
+        }
+
+        changed
+    }
+
+    /// Returns `true` if the set is empty.
+    pub fn is_empty(&self) -> bool {
+        self.words.iter().all(|&word| word == 0)
+    }
+
+    /// Returns the number of values in the set.
+    pub fn len(&self) -> usize {
+        self.words
+            .iter()
+            .map(|word| word.count_ones() as usize)
+            .sum()
+    }
+
+    /// Returns an iterator over the values in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.words.iter().enumerate().flat_map(|(idx, &word)| {
+            let mut word = word;
+
+            std::iter::from_fn(move || {
+                if word == 0 {
+                    return None;
+                }
+
+                let bit = word.trailing_zeros() as usize;
+                word &= word - 1;
+                Some(idx * WORD_BITS + bit)
+            })
+        })
+    }
+
+    /// Returns the values in ascending order.
+    pub fn to_vec(&self) -> Vec<usize> {
+        self.iter().collect()
+    }
+
+    /// Returns the words without the trailing empty words.
+    fn trimmed(&self) -> &[u64] {
+        let length = self
+            .words
+            .iter()
+            .rposition(|&word| word != 0)
+            .map_or(0, |idx| idx + 1);
+        &self.words[..length]
+    }
+}
+
+impl PartialEq for BitSet {
+    fn eq(&self, other: &BitSet) -> bool {
+        self.trimmed() == other.trimmed()
+    }
+}
+
+impl Eq for BitSet {}
+
+impl Hash for BitSet {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.trimmed().hash(state);
+    }
+}
+
+impl FromIterator<usize> for BitSet {
+    fn from_iter<I: IntoIterator<Item = usize>>(iter: I) -> BitSet {
+        let mut set = BitSet::new();
+
+        for value in iter {
+            set.insert(value);
+        }
+
+        set
+    }
+}
+
+impl Extend<usize> for BitSet {
+    fn extend<I: IntoIterator<Item = usize>>(&mut self, iter: I) {
+        for value in iter {
+            self.insert(value);
+        }
+    }
+}
+
+impl Debug for BitSet {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl Encode for BitSet {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.write(&self.trimmed().to_vec());
+    }
+
+    fn decode(decoder: &mut Decoder) -> Result<BitSet, Error> {
+        let words = decoder.read()?;
+        Ok(BitSet { words })
+    }
+}
@@ -0,0 +1,251 @@
+use std::collections::HashMap;
+use std::error;
+use std::fmt::{self, Display, Formatter};
+use std::hash::Hash;
+
+/// The `Encode` trait converts a value to and from a compact binary format,
+/// which is used to save the parse tables to a file.
+pub trait Encode: Sized {
+    fn encode(&self, encoder: &mut Encoder);
+    fn decode(decoder: &mut Decoder) -> Result<Self, Error>;
+}
+
+/// The `Encoder` struct writes values to a byte buffer.
+/// Integers are written as variable-length quantities, so small integers use a single byte.
+#[derive(Clone, Debug, Default)]
+pub struct Encoder {
+    bytes: Vec<u8>,
+}
+
+impl Encoder {
+    /// Constructs a new empty encoder.
+    pub fn new() -> Encoder {
+        Encoder::default()
+    }
+
+    /// Writes the value.
+    pub fn write<T: Encode>(&mut self, value: &T) {
+        value.encode(self);
+    }
+
+    /// Writes the bytes without their length.
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.bytes.extend_from_slice(bytes);
+    }
+
+    /// Writes an integer, 7 bits at a time.
+    pub fn write_u64(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.bytes.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+
+        self.bytes.push(value as u8);
+    }
+
+    /// Returns the written bytes.
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+/// The `Decoder` struct reads values from a byte buffer, which was written by an `Encoder`.
+#[derive(Clone, Debug)]
+pub struct Decoder<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Decoder<'a> {
+    /// Constructs a new decoder for the bytes.
+    pub fn new(bytes: &'a [u8]) -> Decoder<'a> {
+        Decoder { bytes, offset: 0 }
+    }
+
+    /// Reads a value.
+    pub fn read<T: Encode>(&mut self) -> Result<T, Error> {
+        T::decode(self)
+    }
+
+    /// Reads the specified number of bytes.
+    pub fn read_bytes(&mut self, count: usize) -> Result<&'a [u8], Error> {
+        let end = self.offset.checked_add(count).ok_or(Error::Eof)?;
+        let bytes = self.bytes.get(self.offset..end).ok_or(Error::Eof)?;
+        self.offset = end;
+        Ok(bytes)
+    }
+
+    /// Reads an integer, 7 bits at a time.
+    pub fn read_u64(&mut self) -> Result<u64, Error> {
+        let mut value = 0;
+
+        for shift in (0..u64::BITS).step_by(7) {
+            let byte = self.read_bytes(1)?[0];
+            let bits = u64::from(byte & 0x7f);
+
+            if bits << shift >> shift != bits {
+                return Err(Error::Invalid);
+            }
+
+            value |= bits << shift;
+
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+
+        Err(Error::Invalid)
+    }
+
+    /// Returns `true` if all bytes were read.
+    pub fn is_empty(&self) -> bool {
+        self.offset == self.bytes.len()
+    }
+}
+
+/// Returns the 64-bit FNV-1a hash of the bytes.
+///
+/// Unlike the hashers of the standard library, the hash is the same on all platforms
+/// and compiler versions, so it can be saved to a file.
+pub fn hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+impl Encode for u64 {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.write_u64(*self);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Result<u64, Error> {
+        decoder.read_u64()
+    }
+}
+
+impl Encode for usize {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.write_u64(*self as u64);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Result<usize, Error> {
+        usize::try_from(decoder.read_u64()?).map_err(|_| Error::Invalid)
+    }
+}
+
+impl Encode for bool {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.write_u64(u64::from(*self));
+    }
+
+    fn decode(decoder: &mut Decoder) -> Result<bool, Error> {
+        match decoder.read_u64()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(Error::Invalid),
+        }
+    }
+}
+
+impl Encode for String {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.write(&self.len());
+        encoder.write_bytes(self.as_bytes());
+    }
+
+    fn decode(decoder: &mut Decoder) -> Result<String, Error> {
+        let length = decoder.read()?;
+        let bytes = decoder.read_bytes(length)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| Error::Invalid)
+    }
+}
+
+impl<T: Encode> Encode for Option<T> {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.write(&self.is_some());
+
+        if let Some(value) = self {
+            encoder.write(value);
+        }
+    }
+
+    fn decode(decoder: &mut Decoder) -> Result<Option<T>, Error> {
+        match decoder.read()? {
+            true => Ok(Some(decoder.read()?)),
+            false => Ok(None),
+        }
+    }
+}
+
+impl<A: Encode, B: Encode> Encode for (A, B) {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.write(&self.0);
+        encoder.write(&self.1);
+    }
+
+    fn decode(decoder: &mut Decoder) -> Result<(A, B), Error> {
+        Ok((decoder.read()?, decoder.read()?))
+    }
+}
+
+impl<T: Encode> Encode for Vec<T> {
+    fn encode(&self, encoder: &mut Encoder) {
+        encoder.write(&self.len());
+
+        for value in self {
+            encoder.write(value);
+        }
+    }
+
+    fn decode(decoder: &mut Decoder) -> Result<Vec<T>, Error> {
+        let length: usize = decoder.read()?;
+        // Every value takes at least one byte, which limits the allocation for invalid data.
+        let mut values = Vec::with_capacity(length.min(decoder.bytes.len()));
+
+        for _ in 0..length {
+            values.push(decoder.read()?);
+        }
+
+        Ok(values)
+    }
+}
+
+/// The entries are written in ascending order of their keys, so equal maps are encoded equally.
+impl<K: Encode + Eq + Hash + Ord, V: Encode> Encode for HashMap<K, V> {
+    fn encode(&self, encoder: &mut Encoder) {
+        let mut entries: Vec<(&K, &V)> = self.iter().collect();
+        entries.sort_unstable_by(|a, b| a.0.cmp(b.0));
+        encoder.write(&entries.len());
+
+        for (key, value) in entries {
+            encoder.write(key);
+            encoder.write(value);
+        }
+    }
+
+    fn decode(decoder: &mut Decoder) -> Result<HashMap<K, V>, Error> {
+        let entries: Vec<(K, V)> = decoder.read()?;
+        Ok(entries.into_iter().collect())
+    }
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Eof,
+    Invalid,
+    Version(usize),
+    Grammar,
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            Self::Eof => write!(f, "Unexpected end of data"),
+            Self::Invalid => write!(f, "Invalid data"),
+            Self::Version(version) => write!(f, "Unsupported version {}", version),
+            Self::Grammar => write!(f, "Data was saved for a different grammar"),
+        }
+    }
+}
+
+impl error::Error for Error {}
@@ -0,0 +1,156 @@
+//! Generates the parsers of the bundled grammars, compiles them in a separate crate, and
+//! compares their rules with the rules of the parsers of syn for the bundled inputs.
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use syn::automaton::Automaton;
+use syn::generator::{self, Language, Method};
+use syn::grammar::{self, Grammar};
+use syn::lexer;
+use syn::parser::{self, Tables};
+use syn::util::AsString;
+
+/// The bundled grammars with their inputs, and whether the grammar is LL(1).
+const INPUTS: [(&str, &str, bool); 6] = [
+    ("c", "main.c", false),
+    ("dragon", "dragon.txt", false),
+    ("layout", "layout.txt", true),
+    ("lllr", "lllr.txt", false),
+    ("prev", "sieve.prev", false),
+    ("terminator", "terminator.txt", true),
+];
+
+/// Returns the parsing methods of the grammar with their names.
+fn methods(ll: bool) -> Vec<(&'static str, Method)> {
+    let mut methods = vec![("lr", Method::Lr), ("lllr", Method::Lllr)];
+
+    if ll {
+        methods.push(("ll", Method::Ll));
+    }
+
+    methods
+}
+
+/// Returns the rules produced by the parser of syn.
+fn parse(path: &Path, grammar: &Grammar, method: Method) -> Vec<String> {
+    let tokens = lexer::get_tokens(path, grammar).unwrap();
+
+    let rules = match method {
+        Method::Ll => parser::parse_ll(tokens.iter(), grammar),
+        Method::Lr => {
+            let data = Automaton::new(grammar, 0).data().unwrap();
+            parser::parse_lr(tokens.iter(), grammar, &data)
+        }
+        Method::Lllr => {
+            let tables = Tables::new(grammar, 1).unwrap();
+            parser::parse_lllr(tokens.iter(), grammar, &tables)
+        }
+    };
+
+    (rules.unwrap().into_iter())
+        .map(|rule| grammar.rule(rule).string(grammar))
+        .collect()
+}
+
+/// Writes the manifest and the main file of a crate, which includes the generated modules,
+/// and parses the input file with the module named by its first argument.
+fn write_crate(directory: &Path, modules: &[String]) {
+    let manifest = [
+        "[package]",
+        "name = \"generated\"",
+        "version = \"0.0.0\"",
+        "edition = \"2024\"",
+        "",
+        "[dependencies]",
+        "regex = \"1.11.1\"",
+        "",
+        "[workspace]",
+    ];
+
+    fs::write(directory.join("Cargo.toml"), manifest.join("\n") + "\n").unwrap();
+
+    // The versions of the dependencies are the same as for syn.
+    let lock = Path::new(env!("CARGO_MANIFEST_DIR")).join("Cargo.lock");
+    if lock.exists() {
+        fs::copy(lock, directory.join("Cargo.lock")).unwrap();
+    }
+
+    let mut lines = Vec::new();
+
+    for module in modules {
+        lines.push("#[allow(dead_code)]".to_owned());
+        lines.push(format!("mod {} {{ include!(\"{}.rs\"); }}", module, module));
+    }
+
+    lines.push("fn main() {".to_owned());
+    lines.push("    let args: Vec<String> = std::env::args().collect();".to_owned());
+    lines.push("    let text = std::fs::read_to_string(&args[2]).unwrap();".to_owned());
+    lines.push("    let rules: Vec<String> = match args[1].as_str() {".to_owned());
+
+    for module in modules {
+        lines.push(format!(
+            "        \"{0}\" => {0}::parse(&text).unwrap().into_iter().map({0}::rule_string).collect(),",
+            module
+        ));
+    }
+
+    lines.push("        module => panic!(\"Unknown module {}\", module),".to_owned());
+    lines.push("    };".to_owned());
+    lines.push("    for rule in rules {".to_owned());
+    lines.push("        println!(\"{}\", rule);".to_owned());
+    lines.push("    }".to_owned());
+    lines.push("}".to_owned());
+
+    fs::write(directory.join("src/main.rs"), lines.join("\n") + "\n").unwrap();
+}
+
+#[test]
+fn generated_parsers() {
+    let directory = Path::new(env!("CARGO_TARGET_TMPDIR")).join("generated");
+    let source = directory.join("src");
+    fs::create_dir_all(&source).unwrap();
+
+    let mut runs = Vec::new();
+
+    for (name, input, ll) in INPUTS {
+        let grammar = grammar::read_file(&Path::new("grammar").join(format!("{}.toml", name)));
+        let grammar = grammar.unwrap();
+        let path = Path::new("input").join(input);
+
+        for (suffix, method) in methods(ll) {
+            let module = format!("{}_{}", name, suffix);
+            let code = generator::generate(&grammar, Language::Rust, method, 1).unwrap();
+            fs::write(source.join(format!("{}.rs", module)), code).unwrap();
+
+            runs.push((module, path.clone(), parse(&path, &grammar, method)));
+        }
+    }
+
+    let modules: Vec<String> = runs.iter().map(|(module, _, _)| module.clone()).collect();
+    write_crate(&directory, &modules);
+
+    let status = Command::new(env!("CARGO"))
+        .args(["build", "--quiet", "--manifest-path"])
+        .arg(directory.join("Cargo.toml"))
+        .status()
+        .unwrap();
+    assert!(status.success(), "The generated parsers cannot be compiled");
+
+    let binary = directory.join("target/debug/generated");
+
+    for (module, path, expected) in runs {
+        let output = Command::new(&binary)
+            .arg(&module)
+            .arg(&path)
+            .output()
+            .unwrap();
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(output.status.success(), "{}: {}", module, stderr);
+
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let actual: Vec<&str> = stdout.lines().collect();
+        assert_eq!(actual, expected, "{} {}", module, path.display());
+    }
+}
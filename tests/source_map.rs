@@ -0,0 +1,88 @@
+//! Converts between byte offsets, positions and UTF-16 positions in the files of a source map.
+
+use syn::lexer::{SourceFile, SourceMap};
+
+/// Checks that each character boundary of the file converts to a position and back.
+fn round_trip(file: &SourceFile) {
+    for (offset, _) in file.text.char_indices().chain([(file.text.len(), ' ')]) {
+        let position = file.position(offset);
+        assert_eq!(file.offset(position), Some(offset), "{:?}", position);
+
+        let position = file.utf16_position(offset);
+        assert_eq!(file.utf16_offset(position), Some(offset), "{:?}", position);
+    }
+}
+
+#[test]
+fn tabs() {
+    let mut map = SourceMap::new(4);
+    let id = map.add("tabs".to_owned(), "\tab\n  \tc\n".to_owned());
+    let file = map.file(id);
+
+    // A tab advances the column to the next multiple of the tab width.
+    assert_eq!(file.position(1), (1, 5));
+    assert_eq!(file.position(6), (2, 3));
+    assert_eq!(file.position(7), (2, 5));
+    assert_eq!(file.position(9), (3, 1));
+
+    assert_eq!(file.offset((1, 5)), Some(1));
+    assert_eq!(file.offset((2, 5)), Some(7));
+    assert_eq!(file.offset((2, 6)), Some(8));
+
+    // Columns inside a tab don't exist.
+    assert_eq!(file.offset((1, 2)), None);
+    assert_eq!(file.offset((2, 4)), None);
+
+    // A tab is a single UTF-16 code unit.
+    assert_eq!(file.utf16_position(7), (2, 4));
+    assert_eq!(file.utf16_offset((2, 4)), Some(7));
+
+    round_trip(file);
+}
+
+#[test]
+fn multibyte() {
+    let mut map = SourceMap::new(1);
+    let id = map.add("multibyte".to_owned(), "aé€\nü".to_owned());
+    let file = map.file(id);
+
+    assert_eq!(file.position(1), (1, 2));
+    assert_eq!(file.position(3), (1, 3));
+    assert_eq!(file.position(6), (1, 4));
+    assert_eq!(file.position(9), (2, 2));
+
+    assert_eq!(file.offset((1, 3)), Some(3));
+    assert_eq!(file.offset((2, 2)), Some(9));
+    assert_eq!(file.offset((1, 5)), None);
+
+    // Characters in the basic multilingual plane are single UTF-16 code units.
+    assert_eq!(file.utf16_position(6), (1, 4));
+    assert_eq!(file.utf16_offset((1, 4)), Some(6));
+
+    round_trip(file);
+}
+
+#[test]
+fn astral() {
+    let mut map = SourceMap::new(1);
+    let id = map.add("astral".to_owned(), "x😀y\n😀".to_owned());
+    let file = map.file(id);
+
+    assert_eq!(file.position(5), (1, 3));
+    assert_eq!(file.offset((1, 3)), Some(5));
+    assert_eq!(file.position(11), (2, 2));
+
+    // Characters outside the basic multilingual plane are surrogate pairs in UTF-16.
+    assert_eq!(file.utf16_position(1), (1, 2));
+    assert_eq!(file.utf16_position(5), (1, 4));
+    assert_eq!(file.utf16_position(11), (2, 3));
+
+    assert_eq!(file.utf16_offset((1, 4)), Some(5));
+    assert_eq!(file.utf16_offset((2, 3)), Some(11));
+
+    // Columns between the surrogates of a pair don't exist.
+    assert_eq!(file.utf16_offset((1, 3)), None);
+    assert_eq!(file.utf16_offset((2, 2)), None);
+
+    round_trip(file);
+}
@@ -0,0 +1,204 @@
+//! Tokenizes inputs with the bundled grammars, and checks the tokens chosen by the matching
+//! policies and the priorities.
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use syn::grammar::{self, Grammar, Symbol};
+use syn::lexer::{self, FileId, Lexer};
+
+const LONGEST: &str = "matching = \"longest\"";
+const FIRST: &str = "matching = \"first\"";
+
+/// Numbers the temporary grammar files, because the tests run in parallel.
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Returns the sorted paths of the files in the directory.
+fn files(directory: &str) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(directory)
+        .unwrap()
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .collect();
+
+    paths.sort();
+    paths
+}
+
+/// Reads the grammar file with additional header entries and tables.
+fn read_grammar(path: &Path, header: &str, tables: &str) -> Result<Grammar, String> {
+    let contents = fs::read_to_string(path).unwrap();
+    let name = path.file_name().unwrap().to_string_lossy();
+
+    let count = COUNTER.fetch_add(1, Ordering::Relaxed);
+    let temporary = env::temp_dir().join(format!("syn-{}-{}-{}", process::id(), count, name));
+    fs::write(&temporary, format!("{}\n{}\n{}", header, contents, tables)).unwrap();
+
+    let grammar = grammar::read_file(&temporary).map_err(|error| error.to_string());
+    fs::remove_file(&temporary).unwrap();
+    grammar
+}
+
+/// Returns the name of the token as a quoted key for the `[priorities]` table.
+fn token_key(grammar: &Grammar, id: usize) -> String {
+    match grammar.symbol(id) {
+        Symbol::Terminal(_, name) | Symbol::Ignored(_, name) => format!("{:?}", name),
+        symbol => panic!("Symbol {} is not a token", symbol),
+    }
+}
+
+/// Returns the symbols and the lexemes of the tokens in the text, without the final `$`.
+fn tokenize(text: &str, grammar: &Grammar) -> Vec<(String, String)> {
+    let (tokens, diagnostics) = Lexer::from_text(text, FileId::default(), grammar, false)
+        .tokenize()
+        .unwrap();
+
+    assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+
+    (tokens.iter())
+        .filter(|token| token.symbol != Symbol::End.id())
+        .map(|token| {
+            let symbol = grammar.symbol(token.symbol).name();
+            (symbol, token.lexeme.to_string())
+        })
+        .collect()
+}
+
+/// Returns the pairs of symbols and lexemes as owned strings.
+fn pairs(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+    (pairs.iter())
+        .map(|&(symbol, lexeme)| (symbol.to_owned(), lexeme.to_owned()))
+        .collect()
+}
+
+/// Returns the tokens of the bundled inputs with their spans, and the diagnostics.
+fn input_tokens(grammar: &Grammar) -> Vec<String> {
+    let mut lines = Vec::new();
+
+    for input in files("input") {
+        let (tokens, diagnostics) = match lexer::recover_tokens(&input, grammar) {
+            Ok(result) => result,
+            Err(error) => {
+                lines.push(error.to_string());
+                continue;
+            }
+        };
+
+        lines.extend(tokens.iter().map(|token| {
+            let symbol = grammar.symbol(token.symbol);
+            format!("{} {} {:?}", token.span, symbol, token.lexeme.as_str())
+        }));
+
+        lines.extend(diagnostics.iter().map(ToString::to_string));
+    }
+
+    lines
+}
+
+#[test]
+fn keyword_prefix() {
+    let path = Path::new("grammar/c.toml");
+    let text = "if iffy";
+
+    // The longest match is an identifier, unless the keyword is equally long.
+    let longest = read_grammar(path, LONGEST, "").unwrap();
+    let expected = pairs(&[("'if'", "if"), ("'identifier'", "iffy")]);
+    assert_eq!(tokenize(text, &longest), expected);
+
+    // The keywords from `[rules]` come first, so they match the prefix of the identifier.
+    let first = read_grammar(path, FIRST, "").unwrap();
+    let expected = pairs(&[("'if'", "if"), ("'if'", "if"), ("'identifier'", "fy")]);
+    assert_eq!(tokenize(text, &first), expected);
+}
+
+#[test]
+fn longest_and_first() {
+    let path = Path::new("grammar/c.toml");
+    let text = "a <= b";
+
+    let longest = read_grammar(path, LONGEST, "").unwrap();
+    let expected = pairs(&[("'identifier'", "a"), ("'<='", "<="), ("'identifier'", "b")]);
+    assert_eq!(tokenize(text, &longest), expected);
+
+    // The '<' token comes before '<=' in the rules.
+    let first = read_grammar(path, FIRST, "").unwrap();
+    let expected = pairs(&[
+        ("'identifier'", "a"),
+        ("'<'", "<"),
+        ("'='", "="),
+        ("'identifier'", "b"),
+    ]);
+    assert_eq!(tokenize(text, &first), expected);
+}
+
+#[test]
+fn priority_order() {
+    let path = Path::new("grammar/c.toml");
+
+    // A priority takes precedence over the order of the tokens in the grammar file.
+    let tables = "[priorities]\nidentifier = 1";
+    let longest = read_grammar(path, LONGEST, tables).unwrap();
+    let expected = pairs(&[("'identifier'", "if"), ("'identifier'", "iffy")]);
+    assert_eq!(tokenize("if iffy", &longest), expected);
+
+    let tables = "[priorities]\n\"<=\" = 1";
+    let first = read_grammar(path, FIRST, tables).unwrap();
+    let expected = pairs(&[("'identifier'", "a"), ("'<='", "<="), ("'identifier'", "b")]);
+    assert_eq!(tokenize("a <= b", &first), expected);
+}
+
+#[test]
+fn default_priorities() {
+    for path in files("grammar") {
+        let Ok(grammar) = read_grammar(&path, "", "") else {
+            continue;
+        };
+
+        let names: Vec<String> = (grammar.lexicon.matchers.iter())
+            .map(|(id, _)| token_key(&grammar, *id))
+            .collect();
+
+        // Equal priorities, and priorities in the order of precedence, keep the order.
+        let equal: Vec<String> = names.iter().map(|name| format!("{} = 0", name)).collect();
+        let ordered: Vec<String> = (names.iter().enumerate())
+            .map(|(idx, name)| format!("{} = {}", name, i64::MAX - idx as i64))
+            .collect();
+
+        let expected = input_tokens(&grammar);
+
+        for priorities in [equal, ordered] {
+            let tables = format!("[priorities]\n{}", priorities.join("\n"));
+            let grammar = read_grammar(&path, "", &tables).unwrap();
+            assert_eq!(expected, input_tokens(&grammar), "{}", path.display());
+        }
+    }
+}
+
+#[test]
+fn minimum_priority() {
+    let path = Path::new("grammar/dragon.toml");
+    let grammar = read_grammar(path, "", "").unwrap();
+
+    let (id, _) = grammar.lexicon.matchers[0];
+    let name = token_key(&grammar, id);
+
+    // The lowest priority moves the first token to the end, without overflowing.
+    let tables = format!("[priorities]\n{} = {}", name, i64::MIN);
+    let lowest = read_grammar(path, "", &tables).unwrap();
+
+    assert_eq!(lowest.lexicon.matchers.last().unwrap().0, id);
+}
+
+#[test]
+fn unknown_priority() {
+    let path = Path::new("grammar/dragon.toml");
+
+    // Priorities are only assigned to tokens.
+    for name in ["unknown", "E"] {
+        let tables = format!("[priorities]\n{} = 1", name);
+        let error = read_grammar(path, "", &tables).err().unwrap();
+        assert_eq!(error, format!("Cannot parse priority for {}", name));
+    }
+}
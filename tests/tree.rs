@@ -0,0 +1,108 @@
+//! Builds the syntax trees of the bundled inputs with each parser, and checks that the
+//! trees reproduce the input text exactly.
+
+use std::fs;
+use std::path::Path;
+
+use syn::automaton::Automaton;
+use syn::grammar::{self, Grammar, Symbol};
+use syn::lexer::{self, FileId, Lexer, Token};
+use syn::parser::{self, Tables};
+use syn::tree::{self, Derivation, SyntaxNode};
+
+/// The bundled grammars with their inputs, and whether the grammar is LL(1).
+const INPUTS: [(&str, &str, bool); 6] = [
+    ("c.toml", "main.c", false),
+    ("dragon.toml", "dragon.txt", false),
+    ("layout.toml", "layout.txt", true),
+    ("lllr.toml", "lllr.txt", false),
+    ("prev.toml", "sieve.prev", false),
+    ("terminator.toml", "terminator.txt", true),
+];
+
+fn read_grammar(name: &str) -> Grammar {
+    grammar::read_file(&Path::new("grammar").join(name)).unwrap()
+}
+
+/// Returns the syntax tree of the tokens produced by the LR parser.
+fn build_lr(tokens: &[Token], grammar: &Grammar) -> SyntaxNode {
+    let data = Automaton::new(grammar, 0).data().unwrap();
+    let rules = parser::parse_lr(tokens.iter(), grammar, &data).unwrap();
+    tree::build(tokens, &rules, grammar, Derivation::Rightmost).unwrap()
+}
+
+/// Returns the syntax tree of the text produced by the LR parser.
+fn parse_text(text: &str, grammar: &Grammar) -> SyntaxNode {
+    let (tokens, _) = Lexer::from_text(text, FileId::default(), grammar, false)
+        .tokenize()
+        .unwrap();
+
+    build_lr(&tokens, grammar)
+}
+
+/// Returns the nodes with the symbol in preorder.
+fn find_nodes(root: &SyntaxNode, grammar: &Grammar, name: &str) -> Vec<SyntaxNode> {
+    let symbol = (grammar.symbols.iter())
+        .position(|symbol| matches!(symbol, Symbol::NonTerminal(_, other) if other == name))
+        .unwrap();
+
+    (root.descendants().into_iter())
+        .filter(|node| node.symbol() == symbol)
+        .collect()
+}
+
+#[test]
+fn unparse() {
+    for (name, input, ll) in INPUTS {
+        let grammar = read_grammar(name);
+        let path = Path::new("input").join(input);
+        let text = fs::read_to_string(&path).unwrap();
+        let tokens = lexer::get_tokens(&path, &grammar).unwrap();
+
+        let mut trees = vec![("lr", build_lr(&tokens, &grammar))];
+
+        let tables = Tables::new(&grammar, 1).unwrap();
+        let rules = parser::parse_lllr(tokens.iter(), &grammar, &tables).unwrap();
+        let root = tree::build(&tokens, &rules, &grammar, Derivation::Leftmost).unwrap();
+        trees.push(("lllr", root));
+
+        if ll {
+            let rules = parser::parse_ll(tokens.iter(), &grammar).unwrap();
+            let root = tree::build(&tokens, &rules, &grammar, Derivation::Leftmost).unwrap();
+            trees.push(("ll", root));
+        }
+
+        for (parser, root) in trees {
+            assert_eq!(root.unparse(), text, "{} {}", input, parser);
+        }
+    }
+}
+
+#[test]
+fn replace() {
+    let grammar = read_grammar("c.toml");
+    let main = fs::read_to_string("input/main.c").unwrap();
+
+    // The function of the input is surrounded by other functions.
+    let text = format!("int f() {{\n    return 1;\n}}\n\n{}\nint g() {{}}\n", main);
+    let root = parse_text(&text, &grammar);
+
+    let other = parse_text("void h(void) {\n    exit(0);\n}\n", &grammar);
+    let replacement = find_nodes(&other, &grammar, "function_definition").remove(0);
+
+    let node = find_nodes(&root, &grammar, "function_definition").remove(1);
+    let (start, end) = (node.offset(), node.offset() + node.green().width());
+    assert!(node.unparse().contains("main("));
+
+    let changed = node.replace(replacement.green().as_ref().clone());
+
+    // Only the byte range of the replaced node differs, and the original tree is unchanged.
+    let expected = [&text[..start], &replacement.unparse(), &text[end..]].concat();
+    assert_eq!(changed.unparse(), expected);
+    assert_eq!(root.unparse(), text);
+
+    let nodes = find_nodes(&changed, &grammar, "function_definition");
+    assert_eq!(nodes[1].offset(), start);
+    assert_eq!(nodes[1].unparse(), replacement.unparse());
+    assert_eq!(nodes[2].offset(), start + replacement.green().width());
+}
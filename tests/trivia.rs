@@ -0,0 +1,58 @@
+//! Attaches the ignored tokens to the surrounding tokens as trivia.
+
+use std::path::Path;
+
+use syn::grammar::{self, Grammar};
+use syn::lexer::{self, FileId, Lexer, Token};
+
+/// Returns the lexemes of the trivia with their spans, byte offsets and lengths.
+fn trivia(tokens: &[Token]) -> Vec<String> {
+    (tokens.iter())
+        .map(|token| {
+            let span = token.span;
+            let lexeme = token.lexeme.escape_debug();
+            format!("'{}' {} {}+{}", lexeme, span, span.offset, span.len)
+        })
+        .collect()
+}
+
+fn tokenize(text: &str, grammar: &Grammar) -> Vec<Token> {
+    let (tokens, diagnostics) = Lexer::from_text(text, FileId::default(), grammar, false)
+        .tokenize()
+        .unwrap();
+
+    assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+    assert_eq!(lexer::unparse(&tokens), text);
+    tokens
+}
+
+#[test]
+fn split_line_break() {
+    let grammar = grammar::read_file(Path::new("grammar/dragon.toml")).unwrap();
+
+    // The whitespace after '+' is a single ignored token.
+    let tokens = tokenize("1 +  \n\n  2\n", &grammar);
+    let lexemes: Vec<&str> = tokens.iter().map(|token| token.lexeme.as_str()).collect();
+    assert_eq!(lexemes, ["1", "+", "2", "$"]);
+
+    assert_eq!(trivia(&tokens[0].trailing), ["' ' 1:2 1+1"]);
+    assert!(tokens[1].leading.is_empty());
+
+    // The trailing trivia ends with the first line break.
+    assert_eq!(trivia(&tokens[1].trailing), ["'  \\n' 1:4-6 3+3"]);
+    assert_eq!(trivia(&tokens[2].leading), ["'\\n  ' 2:1-3:2 6+3"]);
+
+    assert_eq!(trivia(&tokens[2].trailing), ["'\\n' 3:4 10+1"]);
+    assert!(tokens[3].leading.is_empty());
+}
+
+#[test]
+fn single_line_break() {
+    let grammar = grammar::read_file(Path::new("grammar/dragon.toml")).unwrap();
+
+    // An ignored token which ends with the line break isn't split.
+    let tokens = tokenize("1\n2 \n", &grammar);
+    assert_eq!(trivia(&tokens[0].trailing), ["'\\n' 1:2 1+1"]);
+    assert!(tokens[1].leading.is_empty());
+    assert_eq!(trivia(&tokens[1].trailing), ["' \\n' 2:2-3 3+2"]);
+}
@@ -0,0 +1,127 @@
+//! Decodes the values of the tokens with the bundled C grammar, and checks the diagnostics
+//! of the lexemes that cannot be decoded.
+
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::process;
+
+use syn::automaton::Automaton;
+use syn::grammar::{self, Grammar, Symbol};
+use syn::lexer::{self, FileId, Lexer, Value};
+use syn::parser;
+
+fn read_grammar() -> Grammar {
+    grammar::read_file(Path::new("grammar/c.toml")).unwrap()
+}
+
+/// Reads the C grammar with an additional `[values]` entry.
+fn read_values(entry: &str) -> Result<Grammar, String> {
+    let contents = fs::read_to_string("grammar/c.toml").unwrap();
+    let contents = contents.replace("[values]", &format!("[values]\n{}", entry));
+
+    let name = entry.split(' ').next().unwrap();
+    let temporary = env::temp_dir().join(format!("syn-{}-{}.toml", process::id(), name));
+    fs::write(&temporary, contents).unwrap();
+
+    let grammar = grammar::read_file(&temporary).map_err(|error| error.to_string());
+    fs::remove_file(&temporary).unwrap();
+    grammar
+}
+
+/// Returns the tokens and the diagnostics of the text.
+fn tokenize(text: &str, grammar: &Grammar) -> (Vec<lexer::Token>, Vec<lexer::Error>) {
+    Lexer::from_text(text, FileId::default(), grammar, false)
+        .tokenize()
+        .unwrap()
+}
+
+#[test]
+fn decoded_values() {
+    let grammar = read_grammar();
+    let (tokens, diagnostics) = tokenize("int x = 42; char *s = \"a\\tb\";", &grammar);
+
+    assert!(diagnostics.is_empty());
+
+    let values: Vec<&Value> = tokens
+        .iter()
+        .filter_map(|token| token.value.as_ref())
+        .collect();
+    assert_eq!(values.len(), 4);
+    assert_eq!(values[1], &Value::Integer(42));
+    assert_eq!(values[3], &Value::String("a\tb".to_owned()));
+}
+
+#[test]
+fn integer_out_of_range() {
+    let grammar = read_grammar();
+    let text = "int x = 99999999999999999999;";
+    let (tokens, diagnostics) = tokenize(text, &grammar);
+
+    // The token is still valid, and the lexer continues after it.
+    let token = tokens
+        .iter()
+        .find(|token| token.lexeme.starts_with('9'))
+        .unwrap();
+    assert_eq!(token.value, None);
+    assert_eq!(tokens.last().unwrap().symbol, Symbol::End.id());
+
+    let [lexer::Error::Value(lexeme, _, span)] = &diagnostics[..] else {
+        panic!("Unexpected diagnostics {:?}", diagnostics);
+    };
+
+    assert_eq!(lexeme, "99999999999999999999");
+    assert_eq!(span.to_string(), token.span.to_string());
+    assert_eq!((span.offset, span.len), (8, 20));
+    assert_eq!(&text[span.offset..span.offset + span.len], lexeme);
+}
+
+#[test]
+fn unknown_escape() {
+    let grammar = read_grammar();
+    let text = "char *s =\n    \"\\a\";";
+    let (tokens, diagnostics) = tokenize(text, &grammar);
+
+    let token = tokens
+        .iter()
+        .find(|token| token.lexeme.starts_with('"'))
+        .unwrap();
+    assert_eq!(token.value, None);
+
+    let [lexer::Error::Value(lexeme, reason, span)] = &diagnostics[..] else {
+        panic!("Unexpected diagnostics {:?}", diagnostics);
+    };
+
+    assert_eq!(lexeme, "\"\\a\"");
+    assert!(reason.contains("escape"), "{}", reason);
+    assert_eq!(span.to_string(), token.span.to_string());
+    assert_eq!((span.offset, span.len), (14, 4));
+    assert_eq!(span.start.0, span.end.0);
+}
+
+#[test]
+fn parse_with_diagnostics() {
+    let grammar = read_grammar();
+    let text = "int main() { return 99999999999999999999; }";
+
+    // The diagnostics don't stop the lexer when it is the source of the parser.
+    let data = Automaton::new(&grammar, 0).data().unwrap();
+    let lexer = Lexer::from_text(text, FileId::default(), &grammar, false);
+    let tokens = tokenize(text, &grammar).0;
+
+    let expected = parser::parse_lr(tokens.iter(), &grammar, &data).unwrap();
+    assert_eq!(parser::parse_lr(lexer, &grammar, &data).unwrap(), expected);
+}
+
+#[test]
+fn unknown_names() {
+    assert!(read_values("type_identifier = \"identifier\"").is_ok());
+
+    // Values are only decoded for terminal symbols.
+    for name in ["unknown", "statement"] {
+        let error = read_values(&format!("{} = \"integer\"", name))
+            .err()
+            .unwrap();
+        assert_eq!(error, format!("Cannot parse value kind for {}", name));
+    }
+}